@@ -0,0 +1,276 @@
+use crate::http::{HeaderNames, Response};
+use std::time::{Duration, SystemTime};
+
+/// Max age of assets which never change, e.g. bundles with a content hash in the name.
+const IMMUTABLE_MAX_AGE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Caching directives sent with a response.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CachePolicy {
+    pub max_age: Option<Duration>,
+    pub immutable: bool,
+    pub no_cache: bool,
+    pub no_store: bool,
+    pub private: bool,
+
+    /// Also send `Expires` header computed from `max_age` for HTTP/1.0 caches.
+    pub expires: bool,
+}
+
+impl CachePolicy {
+    /// Allows caching for `max_age` without revalidation.
+    pub fn max_age(max_age: Duration) -> Self {
+        Self {
+            max_age: Some(max_age),
+            ..Default::default()
+        }
+    }
+
+    /// Allows caching forever, suitable for files with a content hash in the name.
+    pub fn immutable() -> Self {
+        Self {
+            immutable: true,
+            ..Self::max_age(IMMUTABLE_MAX_AGE)
+        }
+    }
+
+    /// Requires revalidation before a cached response is used.
+    pub fn no_cache() -> Self {
+        Self {
+            no_cache: true,
+            ..Default::default()
+        }
+    }
+
+    /// Disables caching completely.
+    pub fn no_store() -> Self {
+        Self {
+            no_store: true,
+            ..Default::default()
+        }
+    }
+
+    /// Forbids storing the response in shared caches.
+    pub fn with_private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    pub fn with_expires(mut self) -> Self {
+        self.expires = true;
+        self
+    }
+
+    /// Builds `Cache-Control` header value, `None` if there are no directives.
+    pub fn header_value(&self) -> Option<String> {
+        let mut directives = Vec::new();
+        if self.private {
+            directives.push("private".to_owned());
+        }
+
+        if self.no_store {
+            directives.push("no-store".to_owned());
+        }
+
+        if self.no_cache {
+            directives.push("no-cache".to_owned());
+        }
+
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+
+        if self.immutable {
+            directives.push("immutable".to_owned());
+        }
+
+        if directives.is_empty() {
+            None
+        } else {
+            Some(directives.join(", "))
+        }
+    }
+
+    /// Adds `Cache-Control` and `Expires` headers to a response.
+    pub fn apply(&self, rsp: &mut Response) {
+        if let Some(value) = self.header_value() {
+            rsp.headers
+                .insert(HeaderNames::CacheControl.as_ref(), &value);
+        }
+
+        match self.max_age {
+            Some(max_age) if self.expires && !self.no_store => {
+                let expires = httpdate::fmt_http_date(SystemTime::now() + max_age);
+                rsp.headers.insert(HeaderNames::Expires.as_ref(), &expires);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rules selecting a [CachePolicy] for static files by path pattern.
+///
+/// Rules are checked in order they were added, the first matching one wins.
+#[derive(Clone, Debug)]
+pub struct CacheControl {
+    rules: Vec<(String, CachePolicy)>,
+    default: CachePolicy,
+    listings: CachePolicy,
+}
+
+impl Default for CacheControl {
+    /// Files and listings are revalidated on every use, which is cheap with `ETag`.
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: CachePolicy::no_cache(),
+            listings: CachePolicy::no_cache(),
+        }
+    }
+}
+
+impl CacheControl {
+    /// Adds a rule for files matching a glob pattern.
+    ///
+    /// `*` matches any characters except `/`, `**` matches across directories and `?` matches
+    /// a single character. Patterns without `/` are matched against the file name only,
+    /// others against the whole path starting with `/`.
+    pub fn with_rule(mut self, pattern: &str, policy: CachePolicy) -> Self {
+        self.rules.push((pattern.to_owned(), policy));
+        self
+    }
+
+    /// Adds a rule for files with an extension.
+    pub fn with_extension(self, ext: &str, policy: CachePolicy) -> Self {
+        self.with_rule(&format!("*.{ext}"), policy)
+    }
+
+    /// Sets a policy for files which don't match any rule.
+    pub fn with_default(mut self, policy: CachePolicy) -> Self {
+        self.default = policy;
+        self
+    }
+
+    /// Sets a policy for directory listings, use [CachePolicy::no_store] to disable caching.
+    pub fn with_listings(mut self, policy: CachePolicy) -> Self {
+        self.listings = policy;
+        self
+    }
+
+    /// Returns a policy for a file with a public path.
+    pub fn file_policy(&self, path: &str) -> &CachePolicy {
+        let path = format!("/{}", path.trim_start_matches('/'));
+        let name = path.rsplit('/').next().unwrap_or_default();
+
+        self.rules
+            .iter()
+            .find(|(pattern, _)| {
+                let text = if pattern.contains('/') { &path } else { name };
+                glob_match(pattern.as_bytes(), text.as_bytes())
+            })
+            .map(|(_, policy)| policy)
+            .unwrap_or(&self.default)
+    }
+
+    pub fn listing_policy(&self) -> &CachePolicy {
+        &self.listings
+    }
+}
+
+/// Matches text against a glob pattern, see [CacheControl::with_rule] for the syntax.
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        // `**/` also matches zero directories.
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_match(rest, text)
+                || (0..text.len())
+                    .filter(|&i| text[i] == b'/')
+                    .any(|i| glob_match(rest, &text[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => matches!(text, [c, tail @ ..] if *c != b'/' && glob_match(rest, tail)),
+        [p, rest @ ..] => matches!(text, [c, tail @ ..] if c == p && glob_match(rest, tail)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        glob_match(pattern.as_bytes(), text.as_bytes())
+    }
+
+    #[test]
+    fn star_stays_in_segment() {
+        assert!(matches("*.js", "app.js"));
+        assert!(matches("*.js", ".js"));
+        assert!(!matches("*.js", "app.json"));
+        assert!(matches("/assets/*.css", "/assets/site.css"));
+        assert!(!matches("/assets/*.css", "/assets/css/site.css"));
+    }
+
+    #[test]
+    fn double_star_crosses_directories() {
+        assert!(matches("/assets/**", "/assets/a/b/c.js"));
+        assert!(matches("/assets/**", "/assets/"));
+        assert!(!matches("/assets/**", "/assets2/a"));
+        assert!(matches("/**/index.html", "/index.html"));
+        assert!(matches("/**/index.html", "/a/b/index.html"));
+        assert!(!matches("/**/index.html", "/a/bindex.html"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(matches("v?.js", "v1.js"));
+        assert!(!matches("v?.js", "v10.js"));
+        assert!(!matches("/a?b", "/a/b"));
+    }
+
+    #[test]
+    fn literal_patterns() {
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+        assert!(matches("/robots.txt", "/robots.txt"));
+        assert!(!matches("/robots.txt", "/robots.txt2"));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = CacheControl::default()
+            .with_rule("/static/**", CachePolicy::immutable())
+            .with_extension("html", CachePolicy::no_store())
+            .with_default(CachePolicy::max_age(Duration::from_secs(60)));
+
+        assert_eq!(
+            rules.file_policy("/static/a.html"),
+            &CachePolicy::immutable()
+        );
+        assert_eq!(rules.file_policy("/docs/a.html"), &CachePolicy::no_store());
+        assert_eq!(
+            rules.file_policy("logo.png").header_value().as_deref(),
+            Some("max-age=60")
+        );
+    }
+
+    #[test]
+    fn header_values() {
+        assert_eq!(
+            CachePolicy::immutable().header_value().as_deref(),
+            Some("max-age=31536000, immutable")
+        );
+        assert_eq!(
+            CachePolicy::no_cache()
+                .with_private()
+                .header_value()
+                .as_deref(),
+            Some("private, no-cache")
+        );
+        assert_eq!(CachePolicy::default().header_value(), None);
+    }
+}
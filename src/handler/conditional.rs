@@ -0,0 +1,472 @@
+use crate::http::{HeaderNames, Headers, Method};
+use httpdate::HttpDate;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Files larger than this get metadata tags even with [ETagMode::ContentHash].
+const HASH_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Number of digests remembered by [Digests].
+const MAX_DIGESTS: usize = 4096;
+
+/// File identity (device, inode), modification time and size.
+type DigestKey = (u64, u64, Duration, u64);
+
+#[derive(Default)]
+struct DigestState {
+    /// Quoted digests with the clock value of their last use.
+    entries: HashMap<DigestKey, (String, u64)>,
+    clock: u64,
+}
+
+/// LRU cache of content digests used by [ETagMode::ContentHash], so unchanged files aren't
+/// read on every request.
+#[derive(Default)]
+pub struct Digests {
+    state: Mutex<DigestState>,
+}
+
+impl Digests {
+    fn get(&self, key: &DigestKey) -> Option<String> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.clock += 1;
+        let clock = state.clock;
+        let (etag, last_used) = state.entries.get_mut(key)?;
+        *last_used = clock;
+        Some(etag.clone())
+    }
+
+    fn insert(&self, key: DigestKey, etag: String) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.entries.len() >= MAX_DIGESTS && !state.entries.contains_key(&key) {
+            let lru = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| *key);
+            if let Some(lru) = lru {
+                state.entries.remove(&lru);
+            }
+        }
+
+        state.clock += 1;
+        let clock = state.clock;
+        state.entries.insert(key, (etag, clock));
+    }
+}
+
+/// Defines how entity tags of static files are computed.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum ETagMode {
+    /// Strong tag based on modification time and size.
+    #[default]
+    Metadata,
+
+    /// Weak tag based on modification time and size.
+    WeakMetadata,
+
+    /// Strong tag based on SHA-256 of file contents.
+    ///
+    /// Digests are cached by each handler, keyed by file identity, modification time and
+    /// size. Files larger than 64 MiB get metadata tags instead, as hashing them would delay
+    /// responses.
+    ContentHash,
+}
+
+impl ETagMode {
+    /// Computes entity tag of a file including quotes and weakness indicator.
+    ///
+    /// Content digests are looked up in and added to `digests`.
+    pub fn compute(
+        &self,
+        file: &fs::File,
+        stat: &fs::Metadata,
+        digests: &Digests,
+    ) -> io::Result<String> {
+        let mtime = stat
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let metadata_tag = format!("\"{:x}-{:x}\"", mtime.as_micros(), stat.len());
+
+        match self {
+            Self::Metadata => Ok(metadata_tag),
+            Self::WeakMetadata => Ok(format!("W/{metadata_tag}")),
+            Self::ContentHash if stat.len() > HASH_MAX_SIZE => Ok(metadata_tag),
+            Self::ContentHash => {
+                let key = file_id(stat).map(|(dev, ino)| (dev, ino, mtime, stat.len()));
+                if let Some(etag) = key.and_then(|key| digests.get(&key)) {
+                    return Ok(etag);
+                }
+
+                let etag = hash_file(file)?;
+                if let Some(key) = key {
+                    digests.insert(key, etag.clone());
+                }
+                Ok(etag)
+            }
+        }
+    }
+}
+
+/// Computes quoted SHA-256 of file contents and rewinds the file.
+fn hash_file(file: &fs::File) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; HASH_BUFFER_SIZE];
+    let mut reader = file;
+    loop {
+        match reader.read(&mut buf)? {
+            0 => break,
+            n => hasher.update(&buf[..n]),
+        }
+    }
+    reader.seek(SeekFrom::Start(0))?;
+
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok(format!("\"{hash}\""))
+}
+
+/// Returns device and inode numbers identifying a file, if the platform has them.
+#[cfg(unix)]
+fn file_id(stat: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((stat.dev(), stat.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_stat: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Outcome of precondition evaluation.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Precondition {
+    /// Request should be processed normally.
+    Proceed,
+
+    /// Client's cached representation is still valid (304).
+    NotModified,
+
+    /// Precondition failed (412).
+    Failed,
+}
+
+/// Evaluates conditional request headers in order defined by RFC 9110, section 13.2.2.
+///
+/// `exists` tells whether the target has a current representation, which `*` matches
+/// even if its entity tag isn't known.
+pub fn evaluate(
+    headers: &Headers,
+    method: Method,
+    exists: bool,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> Precondition {
+    let is_safe = matches!(method, Method::GET | Method::HEAD);
+    let date_header = |name: HeaderNames| {
+        headers
+            .get(name.as_ref())
+            .and_then(|v| v.trim().parse::<HttpDate>().ok())
+    };
+
+    if let Some(if_match) = headers.get(HeaderNames::IfMatch.as_ref()) {
+        if !matches_any(if_match, exists, etag, strong_eq) {
+            return Precondition::Failed;
+        }
+    } else if let (Some(date), Some(modified)) =
+        (date_header(HeaderNames::IfUnmodifiedSince), last_modified)
+    {
+        if HttpDate::from(modified) > date {
+            return Precondition::Failed;
+        }
+    }
+
+    if let Some(if_none_match) = headers.get(HeaderNames::IfNoneMatch.as_ref()) {
+        if matches_any(if_none_match, exists, etag, weak_eq) {
+            return if is_safe {
+                Precondition::NotModified
+            } else {
+                Precondition::Failed
+            };
+        }
+    } else if let (true, Some(date), Some(modified)) = (
+        is_safe,
+        date_header(HeaderNames::IfModifiedSince),
+        last_modified,
+    ) {
+        if HttpDate::from(modified) <= date {
+            return Precondition::NotModified;
+        }
+    }
+
+    Precondition::Proceed
+}
+
+/// Checks `If-Range` header which allows partial response only if representation is unchanged.
+///
+/// Entity tags are compared using strong comparison, dates should match exactly.
+pub fn if_range_matches(
+    headers: &Headers,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> bool {
+    let Some(if_range) = headers.get(HeaderNames::IfRange.as_ref()).map(str::trim) else {
+        return true;
+    };
+
+    if is_entity_tag(if_range) {
+        return etag.is_some_and(|etag| strong_eq(if_range, etag));
+    }
+
+    match (if_range.parse::<HttpDate>(), last_modified) {
+        (Ok(date), Some(modified)) => date == HttpDate::from(modified),
+        _ => false,
+    }
+}
+
+fn is_entity_tag(v: &str) -> bool {
+    v.starts_with('"') || v.starts_with("W/")
+}
+
+/// Checks whether a list of entity tags (or `*`) matches the current tag.
+///
+/// `*` matches any existing representation.
+fn matches_any(list: &str, exists: bool, etag: Option<&str>, eq: fn(&str, &str) -> bool) -> bool {
+    list.split(',').map(str::trim).any(|tag| {
+        if tag == "*" {
+            exists
+        } else {
+            etag.is_some_and(|etag| eq(tag, etag))
+        }
+    })
+}
+
+/// Returns whether `If-Match` or `If-None-Match` contain entity tags other than `*`,
+/// so the current tag is needed to evaluate them.
+pub fn compares_tags(headers: &Headers) -> bool {
+    [HeaderNames::IfMatch, HeaderNames::IfNoneMatch]
+        .iter()
+        .filter_map(|name| headers.get(name.as_ref()))
+        .any(|list| list.split(',').any(|tag| tag.trim() != "*"))
+}
+
+fn strong_eq(a: &str, b: &str) -> bool {
+    !a.starts_with("W/") && !b.starts_with("W/") && a == b
+}
+
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::Duration;
+
+    const ETAG: &str = "\"abc\"";
+
+    fn headers(pairs: &[(HeaderNames, &str)]) -> Headers {
+        let mut headers = Headers::new();
+        for (name, value) in pairs {
+            headers.insert(name.as_ref(), value);
+        }
+        headers
+    }
+
+    fn modified() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    fn get(headers: &Headers) -> Precondition {
+        evaluate(headers, Method::GET, true, Some(ETAG), Some(modified()))
+    }
+
+    #[test]
+    fn if_none_match() {
+        let h = headers(&[(HeaderNames::IfNoneMatch, "\"x\", W/\"abc\"")]);
+        assert_eq!(get(&h), Precondition::NotModified);
+        assert_eq!(
+            evaluate(&h, Method::PUT, true, Some(ETAG), None),
+            Precondition::Failed
+        );
+
+        let h = headers(&[(HeaderNames::IfNoneMatch, "\"x\"")]);
+        assert_eq!(get(&h), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_match_uses_strong_comparison() {
+        let h = headers(&[(HeaderNames::IfMatch, "\"abc\"")]);
+        assert_eq!(get(&h), Precondition::Proceed);
+
+        let h = headers(&[(HeaderNames::IfMatch, "W/\"abc\"")]);
+        assert_eq!(get(&h), Precondition::Failed);
+
+        let h = headers(&[(HeaderNames::IfMatch, "\"abc\"")]);
+        assert_eq!(
+            evaluate(&h, Method::GET, true, Some("W/\"abc\""), None),
+            Precondition::Failed
+        );
+    }
+
+    #[test]
+    fn star_matches_existing_representation() {
+        let if_match = headers(&[(HeaderNames::IfMatch, "*")]);
+        let if_none_match = headers(&[(HeaderNames::IfNoneMatch, "*")]);
+        let put = |h, exists, etag| evaluate(h, Method::PUT, exists, etag, None);
+
+        assert_eq!(put(&if_match, true, None), Precondition::Proceed);
+        assert_eq!(put(&if_match, true, Some(ETAG)), Precondition::Proceed);
+        assert_eq!(put(&if_match, false, None), Precondition::Failed);
+        assert_eq!(put(&if_none_match, true, None), Precondition::Failed);
+        assert_eq!(put(&if_none_match, false, None), Precondition::Proceed);
+    }
+
+    #[test]
+    fn compares_tags_ignores_star() {
+        assert!(!compares_tags(&headers(&[])));
+        assert!(!compares_tags(&headers(&[(HeaderNames::IfMatch, " * ")])));
+        assert!(compares_tags(&headers(&[(HeaderNames::IfNoneMatch, ETAG)])));
+        assert!(compares_tags(&headers(&[
+            (HeaderNames::IfNoneMatch, "*"),
+            (HeaderNames::IfMatch, ETAG)
+        ])));
+    }
+
+    #[test]
+    fn dates_are_ignored_with_entity_tags() {
+        let h = headers(&[
+            (HeaderNames::IfNoneMatch, "\"x\""),
+            (
+                HeaderNames::IfModifiedSince,
+                "Fri, 01 Jan 2100 00:00:00 GMT",
+            ),
+        ]);
+        assert_eq!(get(&h), Precondition::Proceed);
+
+        let h = headers(&[
+            (HeaderNames::IfMatch, ETAG),
+            (
+                HeaderNames::IfUnmodifiedSince,
+                "Thu, 01 Jan 1970 00:00:00 GMT",
+            ),
+        ]);
+        assert_eq!(get(&h), Precondition::Proceed);
+    }
+
+    #[test]
+    fn dates() {
+        let h = headers(&[(
+            HeaderNames::IfModifiedSince,
+            "Fri, 01 Jan 2100 00:00:00 GMT",
+        )]);
+        assert_eq!(get(&h), Precondition::NotModified);
+
+        let h = headers(&[(
+            HeaderNames::IfModifiedSince,
+            "Thu, 01 Jan 1970 00:00:00 GMT",
+        )]);
+        assert_eq!(get(&h), Precondition::Proceed);
+
+        let h = headers(&[(
+            HeaderNames::IfUnmodifiedSince,
+            "Thu, 01 Jan 1970 00:00:00 GMT",
+        )]);
+        assert_eq!(get(&h), Precondition::Failed);
+
+        let h = headers(&[(HeaderNames::IfModifiedSince, "yesterday")]);
+        assert_eq!(get(&h), Precondition::Proceed);
+    }
+
+    #[test]
+    fn if_modified_since_is_ignored_for_writes() {
+        let h = headers(&[(
+            HeaderNames::IfModifiedSince,
+            "Fri, 01 Jan 2100 00:00:00 GMT",
+        )]);
+        assert_eq!(
+            evaluate(&h, Method::PUT, true, Some(ETAG), Some(modified())),
+            Precondition::Proceed
+        );
+    }
+
+    #[test]
+    fn if_range() {
+        assert!(if_range_matches(&headers(&[]), None, None));
+
+        let h = headers(&[(HeaderNames::IfRange, ETAG)]);
+        assert!(if_range_matches(&h, Some(ETAG), None));
+        assert!(!if_range_matches(&h, Some("W/\"abc\""), None));
+        assert!(!if_range_matches(&h, None, None));
+
+        let date = httpdate::fmt_http_date(modified());
+        let h = headers(&[(HeaderNames::IfRange, &date)]);
+        assert!(if_range_matches(&h, Some(ETAG), Some(modified())));
+        assert!(!if_range_matches(
+            &h,
+            Some(ETAG),
+            Some(modified() + Duration::from_secs(1))
+        ));
+    }
+
+    #[test]
+    fn content_hash_is_cached_by_metadata() {
+        let path = std::env::temp_dir().join(format!("etag-test-{}", std::process::id()));
+        fs::File::create(&path)
+            .unwrap()
+            .write_all(b"hello")
+            .unwrap();
+
+        let file = fs::File::open(&path).unwrap();
+        let stat = file.metadata().unwrap();
+        let digests = Digests::default();
+        let etag = ETagMode::ContentHash
+            .compute(&file, &stat, &digests)
+            .unwrap();
+        assert_eq!(
+            etag,
+            "\"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824\""
+        );
+        assert_eq!(digests.state.lock().unwrap().entries.len(), 1);
+        assert_eq!(
+            ETagMode::ContentHash
+                .compute(&file, &stat, &digests)
+                .unwrap(),
+            etag
+        );
+
+        let weak = ETagMode::WeakMetadata
+            .compute(&file, &stat, &digests)
+            .unwrap();
+        assert!(weak.starts_with("W/\""));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn digests_evict_least_recently_used() {
+        let digests = Digests::default();
+        let key = |i| (0, i, Duration::ZERO, 0);
+        for i in 0..MAX_DIGESTS as u64 {
+            digests.insert(key(i), i.to_string());
+        }
+        assert_eq!(digests.get(&key(0)).as_deref(), Some("0"));
+
+        digests.insert(key(u64::MAX), "new".to_owned());
+        assert_eq!(digests.state.lock().unwrap().entries.len(), MAX_DIGESTS);
+        assert_eq!(digests.get(&key(0)).as_deref(), Some("0"));
+        assert_eq!(digests.get(&key(1)), None);
+        assert_eq!(digests.get(&key(u64::MAX)).as_deref(), Some("new"));
+    }
+}
@@ -0,0 +1,114 @@
+use super::mime::{self, MimeTypes};
+use super::path::{self, SymlinkPolicy};
+use crate::http::{escape, Response, StatusCode};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Page used when no error document is found.
+///
+/// `{status}` and `{title}` placeholders are replaced by status code and reason phrase.
+const DEFAULT_TEMPLATE: &str = "<!DOCTYPE html>
+<html><head><meta charset=\"utf-8\"><title>{status} {title}</title></head>
+<body><h1>{status} {title}</h1></body></html>
+";
+
+/// Error documents served by the static handler.
+///
+/// Document of a status is looked up in order:
+/// 1. Path set by [ErrorPages::with_page].
+/// 2. `<code>.html` (e.g. `404.html`) in the directory set by [ErrorPages::with_dir],
+///    which defaults to the static root.
+/// 3. `<class>0x.html` (e.g. `50x.html`) in the same directory.
+///
+/// If none exist, a generic page is rendered from the template.
+#[derive(Clone, Debug)]
+pub struct ErrorPages {
+    dir: Option<PathBuf>,
+    pages: HashMap<u16, PathBuf>,
+    template: String,
+}
+
+impl Default for ErrorPages {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            pages: HashMap::new(),
+            template: DEFAULT_TEMPLATE.to_owned(),
+        }
+    }
+}
+
+impl ErrorPages {
+    /// Sets directory with error documents outside the static root.
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Sets document of a status. Relative paths are resolved against the static root.
+    pub fn with_page(mut self, status: StatusCode, page: impl Into<PathBuf>) -> Self {
+        self.pages.insert(status as u16, page.into());
+        self
+    }
+
+    /// Sets generic page template with `{status}` and `{title}` placeholders.
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = template.to_owned();
+        self
+    }
+
+    /// Creates response with an error document of a status.
+    ///
+    /// Documents in the static root are resolved using the same rules as other files.
+    /// Response never contains details of the error itself.
+    pub fn render<'a>(
+        &self,
+        status: StatusCode,
+        root: &Path,
+        symlinks: SymlinkPolicy,
+        mime_types: &MimeTypes,
+    ) -> Response<'a> {
+        let code = status as u16;
+        let lookup = |name: &Path| match self.dir {
+            Some(ref dir) => Some(dir.join(name)),
+            None => path::resolve(root, &name.to_string_lossy(), symlinks).ok(),
+        };
+
+        let candidates = [
+            self.pages.get(&code).and_then(|page| {
+                if page.is_absolute() {
+                    Some(page.clone())
+                } else {
+                    path::resolve(root, &page.to_string_lossy(), symlinks).ok()
+                }
+            }),
+            lookup(Path::new(&format!("{code}.html"))),
+            lookup(Path::new(&format!("{}0x.html", code / 100))),
+        ];
+
+        for page in candidates.into_iter().flatten() {
+            let Ok(body) = fs::read(&page) else {
+                continue;
+            };
+
+            let content_type = mime_types
+                .lookup(&page.to_string_lossy())
+                .map(mime::with_charset)
+                .unwrap_or_else(|| "text/html; charset=utf-8".to_owned());
+            return Response::new(status)
+                .with_content_type(content_type)
+                .with_content_length(body.len() as u64)
+                .with_body(body);
+        }
+
+        let body = self
+            .template
+            .replace("{status}", &code.to_string())
+            .replace("{title}", &escape::html(status.phrase()));
+        Response::new(status)
+            .with_content_type("text/html; charset=utf-8".to_owned())
+            .with_content_length(body.len() as u64)
+            .with_body(body)
+    }
+}
@@ -0,0 +1,177 @@
+use crate::http::{Body, Compression, Encoding};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Cached representation of a static file.
+pub struct CachedFile {
+    pub data: Arc<[u8]>,
+    pub etag: Option<String>,
+    pub content_type: String,
+    pub last_modified: Option<SystemTime>,
+}
+
+/// Representation is identified by canonical path and on-the-fly content coding.
+type Key = (PathBuf, Option<Encoding>);
+
+struct Entry {
+    file: Arc<CachedFile>,
+
+    /// Source file metadata used to detect changes.
+    modified: Option<SystemTime>,
+    len: u64,
+
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<Key, Entry>,
+    used: u64,
+    clock: u64,
+}
+
+impl State {
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.used -= entry.file.data.len() as u64;
+        }
+    }
+
+    fn evict_lru(&mut self) {
+        let lru = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+
+        if let Some(key) = lru {
+            self.remove(&key);
+        }
+    }
+}
+
+/// In-memory LRU cache of small static files and their compressed variants.
+///
+/// Entries are validated against modification time and size of the file on every lookup,
+/// so changed files are never served from the cache.
+pub struct FileCache {
+    max_bytes: u64,
+    max_file_size: u64,
+    compression: Option<Compression>,
+    state: Mutex<State>,
+}
+
+impl FileCache {
+    /// Creates cache which keeps at most `max_bytes` of file contents.
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            max_file_size: DEFAULT_MAX_FILE_SIZE.min(max_bytes),
+            compression: None,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Sets size of the largest cached file, bigger files are always read from disk.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Enables caching of compressed variants, encoded once using these settings.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn fits(&self, len: u64) -> bool {
+        len <= self.max_file_size
+    }
+
+    /// Returns cached representation if the file hasn't changed since it was cached.
+    pub fn get(
+        &self,
+        path: &Path,
+        encoding: Option<Encoding>,
+        stat: &fs::Metadata,
+    ) -> Option<Arc<CachedFile>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (path.to_path_buf(), encoding);
+        let entry = state.entries.get(&key)?;
+        if entry.modified != stat.modified().ok() || entry.len != stat.len() {
+            state.remove(&key);
+            return None;
+        }
+
+        state.clock += 1;
+        let clock = state.clock;
+        let entry = state.entries.get_mut(&key)?;
+        entry.last_used = clock;
+        Some(entry.file.clone())
+    }
+
+    /// Stores representation of a file, evicting least recently used ones to fit the budget.
+    pub fn insert(
+        &self,
+        path: &Path,
+        encoding: Option<Encoding>,
+        stat: &fs::Metadata,
+        file: CachedFile,
+    ) -> Arc<CachedFile> {
+        let file = Arc::new(file);
+        let size = file.data.len() as u64;
+        if size > self.max_bytes {
+            return file;
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (path.to_path_buf(), encoding);
+        state.remove(&key);
+        while state.used + size > self.max_bytes && !state.entries.is_empty() {
+            state.evict_lru();
+        }
+
+        state.clock += 1;
+        state.used += size;
+        let entry = Entry {
+            file: file.clone(),
+            modified: stat.modified().ok(),
+            len: stat.len(),
+            last_used: state.clock,
+        };
+        state.entries.insert(key, entry);
+        file
+    }
+
+    /// Returns whether compressed variants of the file are cached.
+    pub fn is_compressible(&self, file: &CachedFile) -> bool {
+        self.compression.as_ref().is_some_and(|c| {
+            file.data.len() as u64 >= c.min_size && c.is_compressible_type(&file.content_type)
+        })
+    }
+
+    /// Picks encoding of a compressed variant accepted by a client.
+    pub fn negotiate(&self, file: &CachedFile, accept_encoding: Option<&str>) -> Option<Encoding> {
+        let compression = self.compression.as_ref()?;
+        if !self.is_compressible(file) {
+            return None;
+        }
+        accept_encoding.and_then(|v| compression.negotiate(v))
+    }
+}
+
+/// Compresses cached file contents.
+pub fn encode(data: &Arc<[u8]>, encoding: Encoding) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    encoding
+        .encode(Body::Shared(data.clone()))?
+        .into_reader()?
+        .read_to_end(&mut out)?;
+    Ok(out)
+}
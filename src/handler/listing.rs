@@ -0,0 +1,348 @@
+use super::mime::MimeTypes;
+use super::path::{self, SymlinkPolicy};
+use crate::http::{accept, escape, QueryString};
+use std::cmp::Ordering;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use strum_macros::{AsRefStr, EnumString};
+
+/// Column used to sort directory entries, set by `sort` query parameter.
+#[derive(Copy, Clone, PartialEq, Debug, Default, AsRefStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    Type,
+}
+
+/// Sort direction, set by `order` query parameter.
+#[derive(Copy, Clone, PartialEq, Debug, Default, AsRefStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    fn reverse(self) -> Self {
+        match self {
+            Self::Asc => Self::Desc,
+            Self::Desc => Self::Asc,
+        }
+    }
+}
+
+const HTML: &str = "text/html";
+const JSON: &str = "application/json";
+const PLAIN_TEXT: &str = "text/plain";
+
+/// Representation of a listing, selected by `format` query parameter or `Accept` header.
+#[derive(Copy, Clone, PartialEq, Debug, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Format {
+    Html,
+    Json,
+    #[strum(serialize = "text", serialize = "txt")]
+    Text,
+}
+
+impl Format {
+    /// Picks a format, `format` query parameter takes precedence over `Accept` header.
+    ///
+    /// HTML is used if neither selects a known format.
+    pub fn negotiate(query: Option<&QueryString>, accept: Option<&str>) -> Self {
+        if let Some(format) = query
+            .and_then(|q| q.value("format"))
+            .and_then(|v| v.parse().ok())
+        {
+            return format;
+        }
+
+        match accept.and_then(|v| accept::preferred(v, &[HTML, JSON, PLAIN_TEXT])) {
+            Some(JSON) => Self::Json,
+            Some(PLAIN_TEXT) => Self::Text,
+            _ => Self::Html,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Html => "text/html; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Text => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+/// Single file or subdirectory of a listed directory.
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub content_type: Option<String>,
+}
+
+/// Directory listing of a public path.
+pub struct Listing {
+    /// Public path of the directory, always ends with `/`.
+    pub path: String,
+    pub entries: Vec<Entry>,
+    pub sort: SortKey,
+    pub order: SortOrder,
+}
+
+impl Listing {
+    /// Reads directory entries.
+    ///
+    /// Symlinks which can't be served according to the policy are hidden.
+    pub fn read(
+        root: &Path,
+        dir: &Path,
+        public_path: &str,
+        symlinks: SymlinkPolicy,
+        mime_types: &MimeTypes,
+    ) -> io::Result<Self> {
+        let path = format!("{}/", public_path.trim_end_matches('/'));
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if file_type.is_symlink()
+                && path::resolve(root, &format!("{path}{name}"), symlinks).is_err()
+            {
+                continue;
+            }
+
+            // Follows symlinks, broken ones are skipped.
+            let Ok(stat) = fs::metadata(entry.path()) else {
+                continue;
+            };
+
+            let content_type = if stat.is_dir() {
+                None
+            } else {
+                mime_types.lookup(&name).map(str::to_owned)
+            };
+
+            entries.push(Entry {
+                name,
+                is_dir: stat.is_dir(),
+                len: stat.len(),
+                modified: stat.modified().ok(),
+                content_type,
+            });
+        }
+
+        Ok(Self {
+            path,
+            entries,
+            sort: SortKey::default(),
+            order: SortOrder::default(),
+        })
+    }
+
+    /// Sorts entries using `sort` and `order` query parameters, directories go first.
+    pub fn sort(&mut self, query: Option<&QueryString>) {
+        let param = |key| query.and_then(|q| q.value(key)).unwrap_or_default();
+        self.sort = param("sort").parse().unwrap_or_default();
+        self.order = param("order").parse().unwrap_or_default();
+
+        let (sort, order) = (self.sort, self.order);
+        self.entries.sort_by(|a, b| {
+            let ordering = match sort {
+                SortKey::Name => Ordering::Equal,
+                SortKey::Size => a.len.cmp(&b.len),
+                SortKey::Modified => a.modified.cmp(&b.modified),
+                SortKey::Type => a.content_type.cmp(&b.content_type),
+            }
+            .then_with(|| a.name.cmp(&b.name));
+
+            let ordering = match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            };
+            b.is_dir.cmp(&a.is_dir).then(ordering)
+        });
+    }
+
+    /// Public path of the parent directory, `None` for the root.
+    fn parent(&self) -> Option<&str> {
+        let trimmed = self.path.trim_end_matches('/');
+        trimmed.rfind('/').map(|i| &self.path[..=i])
+    }
+
+    /// Renders listing in a format.
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Html => self.to_html(),
+            Format::Json => self.to_json(),
+            Format::Text => self.to_text(),
+        }
+    }
+
+    /// Renders listing as a JSON object with `path` and `entries` fields.
+    ///
+    /// Modification time is a Unix timestamp, size of directories is `null`.
+    pub fn to_json(&self) -> String {
+        let base = encode_path(&self.path);
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let (suffix, size, content_type) = if entry.is_dir {
+                    ("/", "null".to_owned(), escape::json("directory"))
+                } else {
+                    (
+                        "",
+                        entry.len.to_string(),
+                        entry
+                            .content_type
+                            .as_deref()
+                            .map(escape::json)
+                            .unwrap_or_else(|| "null".to_owned()),
+                    )
+                };
+                let modified = entry
+                    .modified
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs().to_string())
+                    .unwrap_or_else(|| "null".to_owned());
+                let url = format!("{base}{}{suffix}", escape::path_segment(&entry.name));
+
+                format!(
+                    "{{\"name\":{},\"size\":{size},\"modified\":{modified},\"type\":{content_type},\"url\":{}}}",
+                    escape::json(&entry.name),
+                    escape::json(&url),
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"path\":{},\"entries\":[{}]}}\n",
+            escape::json(&self.path),
+            entries.join(",")
+        )
+    }
+
+    /// Renders listing as newline-separated names, directories end with `/`.
+    ///
+    /// Names with control characters are percent-encoded to keep one entry per line.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            let name = if entry.name.contains(char::is_control) {
+                escape::path_segment(&entry.name)
+            } else {
+                entry.name.clone()
+            };
+            let suffix = if entry.is_dir { "/" } else { "" };
+            let _ = writeln!(out, "{name}{suffix}");
+        }
+        out
+    }
+
+    /// Renders listing as HTML page. Names are escaped and links are percent-encoded.
+    pub fn to_html(&self) -> String {
+        let base = encode_path(&self.path);
+        let title = escape::html(&self.path);
+
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\
+             <body>\n<h1>Index of {title}</h1>\n<table>\n<tr>"
+        );
+
+        for (key, label) in [
+            (SortKey::Name, "Name"),
+            (SortKey::Size, "Size"),
+            (SortKey::Modified, "Modified"),
+            (SortKey::Type, "Type"),
+        ] {
+            // Clicking the current column toggles the order.
+            let order = if key == self.sort {
+                self.order.reverse()
+            } else {
+                SortOrder::Asc
+            };
+            let _ = write!(
+                out,
+                "<th><a href=\"?sort={}&amp;order={}\">{label}</a></th>",
+                key.as_ref(),
+                order.as_ref()
+            );
+        }
+        out.push_str("</tr>");
+
+        if let Some(parent) = self.parent() {
+            let _ = write!(
+                out,
+                "\n<tr><td><a href=\"{}\">../</a></td><td></td><td></td><td></td></tr>",
+                encode_path(parent)
+            );
+        }
+
+        for entry in &self.entries {
+            let (suffix, size, content_type) = if entry.is_dir {
+                ("/", "-".to_owned(), "Directory")
+            } else {
+                (
+                    "",
+                    format_size(entry.len),
+                    entry.content_type.as_deref().unwrap_or("-"),
+                )
+            };
+            let modified = entry
+                .modified
+                .map(httpdate::fmt_http_date)
+                .unwrap_or_else(|| "-".to_owned());
+
+            let _ = write!(
+                out,
+                "\n<tr><td><a href=\"{base}{}{suffix}\">{}{suffix}</a></td><td>{size}</td><td>{modified}</td><td>{}</td></tr>",
+                escape::path_segment(&entry.name),
+                escape::html(&entry.name),
+                escape::html(content_type),
+            );
+        }
+
+        out.push_str("\n</table>\n</body></html>\n");
+        out
+    }
+}
+
+/// Percent-encodes each segment of a path.
+pub fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(escape::path_segment)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn format_size(len: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = len as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{len} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
@@ -0,0 +1,343 @@
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Timeout of locks requested without `Timeout` header.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Longest timeout granted to a lock, `Infinite` is capped to it.
+const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// WebDAV write lock of a resource.
+#[derive(Clone, Debug)]
+pub struct Lock {
+    /// `opaquelocktoken:` URI identifying the lock.
+    pub token: String,
+
+    /// Normalized public path of the locked resource without trailing slash.
+    pub path: String,
+
+    /// Whether members of a locked collection are locked too.
+    pub recursive: bool,
+
+    pub exclusive: bool,
+
+    /// `DAV:owner` element sent by the client, kept as an XML fragment.
+    pub owner: Option<String>,
+
+    pub timeout: Duration,
+    expires: Instant,
+}
+
+impl Lock {
+    /// Returns whether the lock applies to a resource at the path.
+    fn covers(&self, path: &str) -> bool {
+        self.path == path || (self.recursive && is_descendant(path, &self.path))
+    }
+}
+
+/// Error of creating a lock.
+#[derive(Debug)]
+pub struct Conflict;
+
+/// In-memory table of active locks, expired locks are dropped lazily.
+#[derive(Default, Debug)]
+pub struct Locks {
+    locks: Mutex<Vec<Lock>>,
+    counter: AtomicU64,
+}
+
+impl Locks {
+    /// Creates a lock unless it conflicts with existing ones.
+    ///
+    /// Exclusive locks conflict with any lock of the same resource, shared locks only
+    /// with exclusive ones.
+    pub fn lock(
+        &self,
+        path: &str,
+        recursive: bool,
+        exclusive: bool,
+        owner: Option<String>,
+        timeout: Option<Duration>,
+    ) -> Result<Lock, Conflict> {
+        let path = key(path);
+        let mut locks = self.active();
+        let conflicts = locks.iter().any(|l| {
+            let overlaps = l.covers(path) || (recursive && is_descendant(&l.path, path));
+            overlaps && (exclusive || l.exclusive)
+        });
+        if conflicts {
+            return Err(Conflict);
+        }
+
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT).min(MAX_TIMEOUT);
+        let lock = Lock {
+            token: self.new_token(path),
+            path: path.to_owned(),
+            recursive,
+            exclusive,
+            owner,
+            timeout,
+            expires: Instant::now() + timeout,
+        };
+        locks.push(lock.clone());
+        Ok(lock)
+    }
+
+    /// Extends a lock of the resource identified by one of the tokens.
+    pub fn refresh(
+        &self,
+        path: &str,
+        tokens: &[String],
+        timeout: Option<Duration>,
+    ) -> Option<Lock> {
+        let path = key(path);
+        let mut locks = self.active();
+        let lock = locks
+            .iter_mut()
+            .find(|l| l.covers(path) && tokens.contains(&l.token))?;
+
+        lock.timeout = timeout.unwrap_or(DEFAULT_TIMEOUT).min(MAX_TIMEOUT);
+        lock.expires = Instant::now() + lock.timeout;
+        Some(lock.clone())
+    }
+
+    /// Removes a lock applying to the resource, returns `false` if there is no such lock.
+    pub fn unlock(&self, path: &str, token: &str) -> bool {
+        let path = key(path);
+        let mut locks = self.active();
+        let len = locks.len();
+        locks.retain(|l| !(l.token == token && l.covers(path)));
+        locks.len() != len
+    }
+
+    /// Returns locks applying to the resource.
+    pub fn discover(&self, path: &str) -> Vec<Lock> {
+        let path = key(path);
+        self.active()
+            .iter()
+            .filter(|l| l.covers(path))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns whether the resource can be modified by a client submitting the tokens.
+    ///
+    /// If `recursive` is set, locks of collection members are checked too. A token of any
+    /// lock of a resource is enough, as other locks of it are shared.
+    pub fn permits(&self, path: &str, tokens: &[String], recursive: bool) -> bool {
+        let path = key(path);
+        let locks = self.active();
+        let relevant: Vec<&Lock> = locks
+            .iter()
+            .filter(|l| l.covers(path) || (recursive && is_descendant(&l.path, path)))
+            .collect();
+
+        relevant.iter().all(|l| {
+            relevant
+                .iter()
+                .any(|other| other.path == l.path && tokens.contains(&other.token))
+        })
+    }
+
+    /// Returns whether a token identifies an active lock.
+    pub fn exists(&self, token: &str) -> bool {
+        self.active().iter().any(|l| l.token == token)
+    }
+
+    /// Removes locks of the resource and its members, e.g. after it's deleted or moved.
+    pub fn remove_tree(&self, path: &str) {
+        let path = key(path);
+        self.active()
+            .retain(|l| l.path != path && !is_descendant(&l.path, path));
+    }
+
+    /// Locks the table and drops expired locks.
+    fn active(&self) -> std::sync::MutexGuard<'_, Vec<Lock>> {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        locks.retain(|l| l.expires > now);
+        locks
+    }
+
+    /// Generates unique token formatted as UUID.
+    fn new_token(&self, path: &str) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let counter = self.counter.fetch_add(1, Ordering::Relaxed);
+
+        let mut hasher = Sha256::new();
+        hasher.update(nanos.to_le_bytes());
+        hasher.update(counter.to_le_bytes());
+        hasher.update(path.as_bytes());
+        let hex: String = hasher.finalize()[..16]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        format!(
+            "opaquelocktoken:{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+}
+
+/// Parses `Timeout` header value, e.g. `Second-3600` or `Infinite`.
+///
+/// The first supported value of a list is used.
+pub fn parse_timeout(value: &str) -> Option<Duration> {
+    value.split(',').map(str::trim).find_map(|t| {
+        if t.eq_ignore_ascii_case("Infinite") {
+            Some(MAX_TIMEOUT)
+        } else {
+            t.strip_prefix("Second-")
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_secs)
+        }
+    })
+}
+
+/// Extracts state tokens from `If` header lists, e.g. `(<opaquelocktoken:...>)`.
+///
+/// Resource tags outside of lists and entity tags are skipped.
+pub fn if_tokens(value: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut in_list = false;
+    let mut rest = value;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '(' => in_list = true,
+            ')' => in_list = false,
+            '<' => {
+                let end = rest.find('>').unwrap_or(rest.len());
+                if in_list {
+                    tokens.push(rest[1..end].to_owned());
+                }
+                rest = &rest[end..];
+            }
+            '[' => rest = &rest[rest.find(']').unwrap_or(rest.len())..],
+            _ => {}
+        }
+        rest = &rest[rest.chars().next().map_or(0, char::len_utf8)..];
+    }
+    tokens
+}
+
+/// Lock table key of a path: collections and files are keyed the same way.
+fn key(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Returns whether a path is inside a collection.
+fn is_descendant(path: &str, collection: &str) -> bool {
+    let collection = collection.trim_end_matches('/');
+    path.strip_prefix(collection)
+        .is_some_and(|rest| rest.starts_with('/') && rest.len() > 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn if_tokens_skips_resource_and_entity_tags() {
+        let value = "</a> (<opaquelocktoken:1> [\"etag\"]) (Not <opaquelocktoken:2>)";
+        assert_eq!(if_tokens(value), ["opaquelocktoken:1", "opaquelocktoken:2"]);
+        assert_eq!(if_tokens("([\"<x>\"] <t>)"), ["t"]);
+        assert!(if_tokens("<http://host/a>").is_empty());
+    }
+
+    #[test]
+    fn if_tokens_handles_malformed_values() {
+        assert_eq!(if_tokens("(<unterminated"), ["unterminated"]);
+        assert!(if_tokens("([unterminated <t>)").is_empty());
+        assert_eq!(if_tokens("(é <ü>)"), ["ü"]);
+        assert!(if_tokens("").is_empty());
+    }
+
+    #[test]
+    fn parse_timeout_picks_first_supported() {
+        assert_eq!(parse_timeout("Second-60"), Some(Duration::from_secs(60)));
+        assert_eq!(parse_timeout("infinite"), Some(MAX_TIMEOUT));
+        assert_eq!(
+            parse_timeout("Extension, Second-5, Infinite"),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(parse_timeout("Second-x"), None);
+    }
+
+    #[test]
+    fn timeouts_are_capped() {
+        let locks = Locks::default();
+        let lock = locks
+            .lock("/a", false, true, None, Some(MAX_TIMEOUT * 2))
+            .unwrap();
+        assert_eq!(lock.timeout, MAX_TIMEOUT);
+        assert!(lock.token.starts_with("opaquelocktoken:"));
+    }
+
+    #[test]
+    fn exclusive_locks_conflict() {
+        let locks = Locks::default();
+        locks.lock("/dir/", true, true, None, None).unwrap();
+        assert!(locks.lock("/dir", false, false, None, None).is_err());
+        assert!(locks.lock("/dir/file", false, true, None, None).is_err());
+        assert!(locks.lock("/", true, false, None, None).is_err());
+        assert!(locks.lock("/dir2", false, true, None, None).is_ok());
+    }
+
+    #[test]
+    fn shared_locks_coexist() {
+        let locks = Locks::default();
+        let a = locks.lock("/f", false, false, None, None).unwrap();
+        let b = locks.lock("/f", false, false, None, None).unwrap();
+        assert_ne!(a.token, b.token);
+        assert!(locks.lock("/f", false, true, None, None).is_err());
+        assert_eq!(locks.discover("/f").len(), 2);
+    }
+
+    #[test]
+    fn permits_requires_token_of_each_locked_resource() {
+        let locks = Locks::default();
+        let dir = locks.lock("/dir", false, true, None, None).unwrap();
+        let file = locks.lock("/dir/file", false, true, None, None).unwrap();
+
+        let both = [dir.token, file.token];
+        assert!(!locks.permits("/dir", &[], false));
+        assert!(locks.permits("/dir", &both[..1], false));
+        assert!(!locks.permits("/dir", &both[..1], true));
+        assert!(locks.permits("/dir", &both, true));
+        assert!(locks.permits("/other", &[], true));
+    }
+
+    #[test]
+    fn unlock_and_remove_tree() {
+        let locks = Locks::default();
+        let a = locks.lock("/a/b", false, true, None, None).unwrap();
+        assert!(!locks.unlock("/a", &a.token));
+        assert!(locks.unlock("/a/b", &a.token));
+        assert!(!locks.exists(&a.token));
+
+        let b = locks.lock("/a/b/c", false, true, None, None).unwrap();
+        locks.remove_tree("/a");
+        assert!(!locks.exists(&b.token));
+    }
+
+    #[test]
+    fn descendants() {
+        assert!(is_descendant("/a/b", "/a"));
+        assert!(is_descendant("/a/b", "/a/"));
+        assert!(!is_descendant("/ab", "/a"));
+        assert!(!is_descendant("/a/", "/a"));
+        assert!(!is_descendant("/a", "/a"));
+    }
+}
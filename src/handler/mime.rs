@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// Number of leading bytes inspected by content sniffing.
+const SNIFF_LEN: usize = 512;
+
+/// Built-in extension table, extensions are lowercase.
+const BUILTIN_TYPES: &[(&str, &str)] = &[
+    // Text
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("xhtml", "application/xhtml+xml"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("txt", "text/plain"),
+    ("text", "text/plain"),
+    ("log", "text/plain"),
+    ("md", "text/markdown"),
+    ("markdown", "text/markdown"),
+    ("ics", "text/calendar"),
+    ("vtt", "text/vtt"),
+    ("xml", "application/xml"),
+    ("xsl", "application/xml"),
+    ("rss", "application/rss+xml"),
+    ("atom", "application/atom+xml"),
+    // Scripts and data
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("cjs", "text/javascript"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("jsonld", "application/ld+json"),
+    ("webmanifest", "application/manifest+json"),
+    ("wasm", "application/wasm"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("toml", "application/toml"),
+    // Images
+    ("png", "image/png"),
+    ("apng", "image/apng"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("svgz", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    // Fonts
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("eot", "application/vnd.ms-fontobject"),
+    // Audio
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("aac", "audio/aac"),
+    ("oga", "audio/ogg"),
+    ("ogg", "audio/ogg"),
+    ("opus", "audio/opus"),
+    ("wav", "audio/wav"),
+    ("flac", "audio/flac"),
+    ("weba", "audio/webm"),
+    ("mid", "audio/midi"),
+    ("midi", "audio/midi"),
+    // Video
+    ("mp4", "video/mp4"),
+    ("m4v", "video/mp4"),
+    ("webm", "video/webm"),
+    ("ogv", "video/ogg"),
+    ("mov", "video/quicktime"),
+    ("avi", "video/x-msvideo"),
+    ("mkv", "video/x-matroska"),
+    ("mpeg", "video/mpeg"),
+    ("mpg", "video/mpeg"),
+    ("ts", "video/mp2t"),
+    ("m3u8", "application/vnd.apple.mpegurl"),
+    // Documents
+    ("pdf", "application/pdf"),
+    ("rtf", "application/rtf"),
+    ("epub", "application/epub+zip"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("xls", "application/vnd.ms-excel"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("ppt", "application/vnd.ms-powerpoint"),
+    (
+        "pptx",
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    ),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+    ("odp", "application/vnd.oasis.opendocument.presentation"),
+    // Archives
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tgz", "application/gzip"),
+    ("bz2", "application/x-bzip2"),
+    ("xz", "application/x-xz"),
+    ("zst", "application/zstd"),
+    ("br", "application/x-brotli"),
+    ("tar", "application/x-tar"),
+    ("7z", "application/x-7z-compressed"),
+    ("rar", "application/vnd.rar"),
+    // Binaries
+    ("bin", DEFAULT_MIME_TYPE),
+    ("exe", "application/vnd.microsoft.portable-executable"),
+    ("dmg", "application/x-apple-diskimage"),
+    ("iso", "application/x-iso9660-image"),
+    ("deb", "application/vnd.debian.binary-package"),
+    ("rpm", "application/x-rpm"),
+    ("apk", "application/vnd.android.package-archive"),
+    ("jar", "application/java-archive"),
+];
+
+/// Maps file extensions to media types.
+///
+/// Built-in table can be extended with `mime.types` files and overrides,
+/// later entries replace earlier ones.
+#[derive(Clone, Debug)]
+pub struct MimeTypes {
+    types: HashMap<String, String>,
+    sniff: bool,
+}
+
+impl Default for MimeTypes {
+    fn default() -> Self {
+        Self {
+            types: BUILTIN_TYPES
+                .iter()
+                .map(|&(ext, mime)| (ext.to_owned(), mime.to_owned()))
+                .collect(),
+            sniff: false,
+        }
+    }
+}
+
+impl MimeTypes {
+    /// Adds types from a file in `/etc/mime.types` format.
+    ///
+    /// Each line contains a media type followed by extensions, `#` starts a comment.
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(mime) = fields.next().filter(|m| m.contains('/')) else {
+                continue;
+            };
+
+            for ext in fields {
+                self.types.insert(ext.to_ascii_lowercase(), mime.to_owned());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Sets media type of an extension, e.g. `("mjs", "text/javascript")`.
+    pub fn with_override(mut self, ext: &str, mime: &str) -> Self {
+        let ext = ext.trim_start_matches('.').to_ascii_lowercase();
+        self.types.insert(ext, mime.to_owned());
+        self
+    }
+
+    /// Enables content sniffing of files without a known extension.
+    pub fn with_sniffing(mut self, sniff: bool) -> Self {
+        self.sniff = sniff;
+        self
+    }
+
+    /// Returns media type registered for an extension of the file.
+    pub fn lookup(&self, path: &str) -> Option<&str> {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.types.get(&ext.to_ascii_lowercase()))
+            .map(String::as_str)
+    }
+
+    /// Returns `Content-Type` of a file including charset for text types.
+    ///
+    /// The file is rewound after sniffing.
+    pub fn content_type(&self, path: &str, file: &fs::File) -> String {
+        let mime = match self.lookup(path) {
+            Some(mime) => mime,
+            None if self.sniff => sniff(file).unwrap_or(DEFAULT_MIME_TYPE),
+            None => DEFAULT_MIME_TYPE,
+        };
+
+        with_charset(mime)
+    }
+}
+
+/// Appends UTF-8 charset to textual types without parameters.
+pub fn with_charset(mime: &str) -> String {
+    let is_text = mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime,
+            "application/json" | "application/xml" | "application/javascript"
+        );
+
+    if is_text && !mime.contains(';') {
+        format!("{mime}; charset=utf-8")
+    } else {
+        mime.to_owned()
+    }
+}
+
+/// Guesses media type using magic numbers, falls back to plain text for UTF-8 content.
+fn sniff(file: &fs::File) -> io::Result<&'static str> {
+    let mut buf = Vec::with_capacity(SNIFF_LEN);
+    let mut reader = file;
+    reader.take(SNIFF_LEN as u64).read_to_end(&mut buf)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (b"\0asm", "application/wasm"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"OggS", "audio/ogg"),
+        (b"ID3", "audio/mpeg"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+    ];
+
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| buf.starts_with(magic)) {
+        return Ok(mime);
+    }
+
+    if buf.len() >= 12 && &buf[..4] == b"RIFF" && &buf[8..12] == b"WEBP" {
+        return Ok("image/webp");
+    }
+
+    if buf.len() >= 12 && &buf[4..8] == b"ftyp" {
+        return Ok("video/mp4");
+    }
+
+    let start = String::from_utf8_lossy(&buf[..buf.len().min(64)])
+        .trim_start()
+        .to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        return Ok("text/html");
+    }
+
+    if start.starts_with("<?xml") {
+        return Ok("application/xml");
+    }
+
+    // The last character may be cut in the middle.
+    let text = match std::str::from_utf8(&buf) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    };
+    let binary = buf
+        .iter()
+        .any(|&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c));
+
+    if text && !binary {
+        Ok("text/plain")
+    } else {
+        Ok(DEFAULT_MIME_TYPE)
+    }
+}
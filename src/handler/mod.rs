@@ -0,0 +1,757 @@
+use anyhow::{Context, Result};
+
+use crate::http::{
+    self, Body, Encoding, Handler, HeaderNames, Headers, Method, ParseError, Problem, QueryString,
+    Request, Response, StatusCode,
+};
+use conditional::{Digests, Precondition};
+use file_cache::CachedFile;
+use listing::{Format, Listing};
+use path::PathError;
+use range::RangeRequest;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+#[allow(unused_imports)]
+pub use cache_control::{CacheControl, CachePolicy};
+pub use conditional::ETagMode;
+pub use error_page::ErrorPages;
+pub use file_cache::FileCache;
+pub use mime::MimeTypes;
+#[allow(unused_imports)]
+pub use mount::{MountMode, Mounts};
+pub use path::SymlinkPolicy;
+pub use try_files::TryFiles;
+pub use upload::Uploads;
+pub use vhost::VirtualHosts;
+pub use webdav::WebDav;
+
+mod cache_control;
+mod conditional;
+mod error_page;
+mod file_cache;
+mod listing;
+mod lock;
+mod mime;
+mod mount;
+mod path;
+mod precompressed;
+mod range;
+#[cfg(test)]
+mod temp_dir;
+mod try_files;
+mod upload;
+mod vhost;
+mod webdav;
+
+const MAX_REQUEST_SIZE: u64 = 1024 * 1024;
+const INDEX_FILE: &str = "index.html";
+
+pub struct StaticHandler {
+    static_dir: PathBuf,
+    symlinks: SymlinkPolicy,
+    etag: ETagMode,
+    digests: Digests,
+    cache_control: CacheControl,
+    mime_types: MimeTypes,
+    precompressed: Vec<Encoding>,
+    dir_listing: bool,
+    error_pages: ErrorPages,
+    try_files: TryFiles,
+    file_cache: Option<FileCache>,
+    index_files: Vec<String>,
+    url_prefix: String,
+    uploads: Option<Uploads>,
+}
+
+/// Regular file selected to be sent.
+struct StaticFile {
+    abspath: PathBuf,
+    file: fs::File,
+    stat: fs::Metadata,
+
+    /// Content coding of a precompressed sidecar file.
+    encoding: Option<Encoding>,
+}
+
+impl StaticHandler {
+    pub fn new(static_dir: String) -> Self {
+        // Canonical root is required to check that resolved files are inside it.
+        let static_dir =
+            fs::canonicalize(&static_dir).unwrap_or_else(|_| PathBuf::from(static_dir));
+        Self {
+            static_dir,
+            symlinks: SymlinkPolicy::default(),
+            etag: ETagMode::default(),
+            digests: Digests::default(),
+            cache_control: CacheControl::default(),
+            mime_types: MimeTypes::default(),
+            precompressed: vec![Encoding::Zstd, Encoding::Brotli, Encoding::Gzip],
+            dir_listing: true,
+            error_pages: ErrorPages::default(),
+            try_files: TryFiles::default(),
+            file_cache: None,
+            index_files: vec![INDEX_FILE.to_owned()],
+            url_prefix: String::new(),
+            uploads: None,
+        }
+    }
+
+    pub fn with_symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    pub fn with_etag(mut self, etag: ETagMode) -> Self {
+        self.etag = etag;
+        self
+    }
+
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = cache_control;
+        self
+    }
+
+    pub fn with_mime_types(mut self, mime_types: MimeTypes) -> Self {
+        self.mime_types = mime_types;
+        self
+    }
+
+    /// Sets encodings of precompressed sidecar files in order of preference.
+    ///
+    /// Sidecars (e.g. `app.js.br`) are served instead of the original file if a client
+    /// accepts their encoding. Empty list disables the lookup.
+    pub fn with_precompressed(mut self, encodings: Vec<Encoding>) -> Self {
+        self.precompressed = encodings;
+        self
+    }
+
+    /// Enables listing of directories without an index file, otherwise they're forbidden.
+    pub fn with_dir_listing(mut self, enabled: bool) -> Self {
+        self.dir_listing = enabled;
+        self
+    }
+
+    pub fn with_error_pages(mut self, error_pages: ErrorPages) -> Self {
+        self.error_pages = error_pages;
+        self
+    }
+
+    /// Sets files tried when the requested path doesn't exist.
+    pub fn with_try_files(mut self, try_files: TryFiles) -> Self {
+        self.try_files = try_files;
+        self
+    }
+
+    /// Enables in-memory cache of small files.
+    pub fn with_file_cache(mut self, file_cache: FileCache) -> Self {
+        self.file_cache = Some(file_cache);
+        self
+    }
+
+    /// Sets names of files served instead of directory listings, checked in order.
+    pub fn with_index_files(mut self, index_files: &[&str]) -> Self {
+        self.index_files = index_files.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Sets URL prefix of generated links when the handler is mounted below `/`.
+    pub fn with_url_prefix(mut self, prefix: &str) -> Self {
+        self.url_prefix = prefix.trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Enables `PUT` and `DELETE` of files in the static root.
+    pub fn with_uploads(mut self, uploads: Uploads) -> Self {
+        self.uploads = Some(uploads);
+        self
+    }
+
+    /// Returns decoded and normalized request path or a status of an error response.
+    fn request_path(req: &Request) -> Result<String, StatusCode> {
+        let path = http::decode_path(req.path()).map_err(|err| {
+            println!("can't decode path {}: {err}", req.path());
+            StatusCode::BadRequest
+        })?;
+        path::normalize(&path).map_err(|err| err.status_code())
+    }
+
+    /// Creates response with an error document, details are only logged.
+    ///
+    /// 401 responses include the authentication challenge of [Uploads].
+    fn error_response<'a>(&self, status: StatusCode) -> Response<'a> {
+        let rsp =
+            self.error_pages
+                .render(status, &self.static_dir, self.symlinks, &self.mime_types);
+        match self.uploads {
+            Some(ref uploads) if matches!(status, StatusCode::Unauthorized) => {
+                rsp.with_header(HeaderNames::WWWAuthenticate.as_ref(), uploads.challenge())
+            }
+            _ => rsp,
+        }
+    }
+
+    fn serve_file<'a>(
+        &self,
+        path: &str,
+        headers: &Headers,
+        query: Option<&QueryString>,
+    ) -> Response<'a> {
+        let open = |path: &str| {
+            path::resolve(&self.static_dir, path, self.symlinks).and_then(|abspath| {
+                let f = fs::File::open(&abspath)?;
+                let stat = f.metadata()?;
+                Ok((abspath, f, stat))
+            })
+        };
+
+        let mut result = open(path);
+        let mut found_path = None;
+        let missing = matches!(result, Err(PathError::NotFound));
+        if missing {
+            let accept = headers.get(HeaderNames::Accept.as_ref());
+            for candidate in self.try_files.candidates(path, accept) {
+                match open(&candidate) {
+                    Ok(found) if found.2.is_file() => {
+                        result = Ok(found);
+                        found_path = Some(candidate);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let path = found_path.as_deref().unwrap_or(path);
+        let mut rsp = match result {
+            Ok((abspath, _, stat)) if stat.is_dir() => {
+                // List directory contents
+                self.serve_dir_list(&abspath, path, headers, query)
+                    .unwrap_or_else(|err| {
+                        println!("failed to list directory {path}: {err}");
+                        self.error_response(StatusCode::Forbidden)
+                    })
+            }
+            Ok((abspath, file, stat)) => {
+                let accept_encoding = headers.get(HeaderNames::AcceptEncoding.as_ref());
+                let selection = precompressed::select(
+                    &self.static_dir,
+                    path,
+                    self.symlinks,
+                    &self.precompressed,
+                    accept_encoding,
+                );
+
+                let original = StaticFile {
+                    abspath,
+                    file,
+                    stat,
+                    encoding: None,
+                };
+                let mut varies = selection.varies;
+                let cached = match self.file_cache {
+                    // Ranges are rare for small files, so they're always read from disk.
+                    Some(ref cache) if !headers.has(HeaderNames::Range.as_ref()) => {
+                        let served = selection.variant.as_ref().unwrap_or(&original);
+                        self.load_cached(cache, &original, served, path, accept_encoding)
+                    }
+                    _ => None,
+                };
+
+                let mut rsp = match cached {
+                    Some((file, encoding, compressible)) => {
+                        varies |= compressible;
+                        self.serve_cached(file, encoding, path, headers)
+                    }
+                    None => {
+                        // Content type is detected using the original file, as sidecars are compressed.
+                        let content_type = self.mime_types.content_type(path, &original.file);
+                        let static_file = selection.variant.unwrap_or(original);
+                        self.serve_regular_file(static_file, path, content_type, headers)
+                    }
+                };
+
+                if varies {
+                    rsp.headers.append(
+                        HeaderNames::Vary.as_ref(),
+                        HeaderNames::AcceptEncoding.as_ref(),
+                    );
+                }
+                rsp
+            }
+            Err(err) => self.error_response(err.status_code()),
+        };
+
+        if missing && self.try_files.varies() {
+            rsp.headers
+                .append(HeaderNames::Vary.as_ref(), HeaderNames::Accept.as_ref());
+        }
+        rsp
+    }
+
+    fn serve_regular_file<'a>(
+        &self,
+        static_file: StaticFile,
+        path: &str,
+        content_type: String,
+        headers: &Headers,
+    ) -> Response<'a> {
+        let StaticFile {
+            abspath,
+            file: f,
+            stat,
+            encoding,
+        } = static_file;
+        let len = stat.len();
+        let last_modified = stat.modified().ok();
+        let etag = self.compute_etag(&f, &stat, encoding, path);
+
+        let rsp =
+            match conditional::evaluate(headers, Method::GET, true, etag.as_deref(), last_modified)
+            {
+                Precondition::NotModified => Response::new(StatusCode::NotModified),
+                Precondition::Failed => self.error_response(StatusCode::PreconditionFailed),
+                Precondition::Proceed => {
+                    let ranges = match headers.get(HeaderNames::Range.as_ref()) {
+                        Some(range)
+                            if conditional::if_range_matches(
+                                headers,
+                                etag.as_deref(),
+                                last_modified,
+                            ) =>
+                        {
+                            range::parse(range, len)
+                        }
+                        _ => RangeRequest::Full,
+                    };
+
+                    match ranges {
+                        RangeRequest::Full => Response::new(StatusCode::OK)
+                            .with_content_length(len)
+                            .with_content_type(content_type)
+                            .with_body(Body::file_range(f, 0, len)),
+                        RangeRequest::Partial(ranges) if ranges.len() == 1 => {
+                            range::single(f, ranges[0].clone(), len).with_content_type(content_type)
+                        }
+                        RangeRequest::Partial(ranges) => {
+                            range::multipart(&abspath, &ranges, len, &content_type).unwrap_or_else(
+                                |err| {
+                                    println!("failed to read ranges of {path}: {err}");
+                                    self.error_response(StatusCode::InternalServerError)
+                                },
+                            )
+                        }
+                        RangeRequest::Unsatisfiable => self
+                            .error_response(StatusCode::RangeNotSatisfiable)
+                            .with_header(
+                                HeaderNames::ContentRange.as_ref(),
+                                &range::unsatisfied_range(len),
+                            ),
+                    }
+                }
+            };
+
+        self.with_file_headers(rsp, path, etag, last_modified, encoding)
+    }
+
+    fn compute_etag(
+        &self,
+        file: &fs::File,
+        stat: &fs::Metadata,
+        encoding: Option<Encoding>,
+        path: &str,
+    ) -> Option<String> {
+        self.etag
+            .compute(file, stat, &self.digests)
+            .map_err(|err| println!("failed to compute ETag of {path}: {err}"))
+            .ok()
+            .map(|etag| with_encoding_suffix(etag, encoding))
+    }
+
+    /// Adds validators, content coding and caching headers to a file response.
+    fn with_file_headers<'a>(
+        &self,
+        rsp: Response<'a>,
+        path: &str,
+        etag: Option<String>,
+        last_modified: Option<SystemTime>,
+        encoding: Option<Encoding>,
+    ) -> Response<'a> {
+        let mut rsp = rsp.with_header(HeaderNames::AcceptRanges.as_ref(), "bytes");
+        if let Some(etag) = etag {
+            rsp.headers.insert(HeaderNames::ETag.as_ref(), &etag);
+        }
+
+        if let Some(last_modified) = last_modified {
+            rsp.headers.insert(
+                HeaderNames::LastModified.as_ref(),
+                &httpdate::fmt_http_date(last_modified),
+            );
+        }
+
+        if matches!(
+            rsp.status_code,
+            StatusCode::OK | StatusCode::PartialContent | StatusCode::NotModified
+        ) {
+            if let Some(encoding) = encoding {
+                rsp.headers
+                    .insert(HeaderNames::ContentEncoding.as_ref(), encoding.as_ref());
+            }
+            self.cache_control.file_policy(path).apply(&mut rsp);
+        }
+        rsp
+    }
+
+    /// Returns cached representation of a file, loading it on a miss.
+    ///
+    /// `served` is either the original file or its precompressed sidecar. Compressed variants
+    /// are created only for original files. Also returns content coding of the representation
+    /// and whether the file has compressed variants.
+    fn load_cached(
+        &self,
+        cache: &FileCache,
+        original: &StaticFile,
+        served: &StaticFile,
+        path: &str,
+        accept_encoding: Option<&str>,
+    ) -> Option<(Arc<CachedFile>, Option<Encoding>, bool)> {
+        if !cache.fits(served.stat.len()) {
+            return None;
+        }
+
+        let file = match cache.get(&served.abspath, None, &served.stat) {
+            Some(file) => file,
+            None => {
+                let content_type = self.mime_types.content_type(path, &original.file);
+                let etag = self.compute_etag(&served.file, &served.stat, served.encoding, path);
+                let mut data = Vec::with_capacity(served.stat.len() as usize);
+                let mut reader = &served.file;
+                if let Err(err) = reader.read_to_end(&mut data) {
+                    println!("failed to read {path}: {err}");
+                    return None;
+                }
+
+                let file = CachedFile {
+                    data: data.into(),
+                    etag,
+                    content_type,
+                    last_modified: served.stat.modified().ok(),
+                };
+                cache.insert(&served.abspath, None, &served.stat, file)
+            }
+        };
+
+        if served.encoding.is_some() {
+            return Some((file, served.encoding, false));
+        }
+
+        let compressible = cache.is_compressible(&file);
+        let Some(encoding) = cache.negotiate(&file, accept_encoding) else {
+            return Some((file, None, compressible));
+        };
+
+        if let Some(variant) = cache.get(&served.abspath, Some(encoding), &served.stat) {
+            return Some((variant, Some(encoding), compressible));
+        }
+
+        match file_cache::encode(&file.data, encoding) {
+            Ok(data) => {
+                let variant = CachedFile {
+                    data: data.into(),
+                    etag: file
+                        .etag
+                        .clone()
+                        .map(|etag| with_encoding_suffix(etag, Some(encoding))),
+                    content_type: file.content_type.clone(),
+                    last_modified: file.last_modified,
+                };
+                let variant = cache.insert(&served.abspath, Some(encoding), &served.stat, variant);
+                Some((variant, Some(encoding), compressible))
+            }
+            Err(err) => {
+                println!("failed to compress {path} with {encoding}: {err}");
+                Some((file, None, compressible))
+            }
+        }
+    }
+
+    fn serve_cached<'a>(
+        &self,
+        file: Arc<CachedFile>,
+        encoding: Option<Encoding>,
+        path: &str,
+        headers: &Headers,
+    ) -> Response<'a> {
+        let etag = file.etag.clone();
+        let rsp = match conditional::evaluate(
+            headers,
+            Method::GET,
+            true,
+            etag.as_deref(),
+            file.last_modified,
+        ) {
+            Precondition::NotModified => Response::new(StatusCode::NotModified),
+            Precondition::Failed => self.error_response(StatusCode::PreconditionFailed),
+            Precondition::Proceed => Response::new(StatusCode::OK)
+                .with_content_length(file.data.len() as u64)
+                .with_content_type(file.content_type.clone())
+                .with_body(file.data.clone()),
+        };
+
+        self.with_file_headers(rsp, path, etag, file.last_modified, encoding)
+    }
+
+    fn serve_dir_list<'a>(
+        &self,
+        path: &Path,
+        public_path: &str,
+        headers: &Headers,
+        query: Option<&QueryString>,
+    ) -> Result<Response<'a>> {
+        if let Some(index_file) = self.index_files.iter().find(|f| path.join(f).is_file()) {
+            let index_path = format!("{}/{index_file}", public_path.trim_end_matches('/'));
+            return Ok(self.serve_file(&index_path, headers, query));
+        }
+
+        if !self.dir_listing {
+            return Ok(self.error_response(StatusCode::Forbidden));
+        }
+
+        let mut listing = Listing::read(
+            &self.static_dir,
+            path,
+            public_path,
+            self.symlinks,
+            &self.mime_types,
+        )?;
+        listing.path.insert_str(0, &self.url_prefix);
+        listing.sort(query);
+
+        let format = Format::negotiate(query, headers.get(HeaderNames::Accept.as_ref()));
+        let body = listing.render(format);
+        let mut rsp = Response::new(StatusCode::OK)
+            .with_content_length(body.len() as u64)
+            .with_content_type(format.content_type().to_owned())
+            .with_header(HeaderNames::Vary.as_ref(), HeaderNames::Accept.as_ref())
+            .with_body(body);
+        self.cache_control.listing_policy().apply(&mut rsp);
+        Ok(rsp)
+    }
+
+    /// Handles `PUT` and `DELETE` after authorisation and path checks.
+    fn handle_write<'a, 'b>(&self, mut req: Request<'a>) -> Response<'b> {
+        let path = match Self::request_path(&req) {
+            Ok(path) => path,
+            Err(status) => return self.error_response(status),
+        };
+
+        let uploads = match self.authorize_write(&req, &path) {
+            Ok(uploads) => uploads,
+            Err(status) => return self.error_response(status),
+        };
+
+        let result = match req.method {
+            Method::PUT => self.put_file(uploads, &mut req, &path),
+            _ => self.delete_file(&req, &path),
+        };
+        result.unwrap_or_else(|status| match status {
+            StatusCode::MethodNotAllowed => self
+                .error_response(status)
+                .with_header(HeaderNames::Allow.as_ref(), "GET, HEAD"),
+            status => self.error_response(status),
+        })
+    }
+
+    /// Checks that a request may modify a path: it must be authorised and allowed by [Uploads].
+    ///
+    /// Without [Uploads] the root is read-only.
+    fn authorize_write(&self, req: &Request, path: &str) -> Result<&Uploads, StatusCode> {
+        let uploads = self.uploads.as_ref().ok_or(StatusCode::Forbidden)?;
+        if !uploads.authorize(req, path) {
+            // Missing credentials can be retried, wrong ones can't.
+            return if req.headers.has(HeaderNames::Authorization.as_ref()) {
+                Err(StatusCode::Forbidden)
+            } else {
+                Err(StatusCode::Unauthorized)
+            };
+        }
+
+        if !uploads.allows(path) {
+            return Err(StatusCode::Forbidden);
+        }
+        Ok(uploads)
+    }
+
+    /// Evaluates `If-Match` and `If-None-Match` against the current file, if any.
+    ///
+    /// `If-None-Match: *` makes sure an existing file isn't overwritten and `If-Match`
+    /// that the replaced version is the one a client has seen.
+    fn check_write_preconditions(
+        &self,
+        headers: &Headers,
+        abspath: &Path,
+        path: &str,
+    ) -> Result<bool, StatusCode> {
+        let current = fs::File::open(abspath).and_then(|f| Ok((f.metadata()?, f)));
+        let (etag, last_modified, exists) = match current {
+            Ok((stat, _)) if stat.is_dir() => return Err(StatusCode::MethodNotAllowed),
+            Ok((stat, file)) => (
+                // Hashing a file for ETagMode::ContentHash is only worth it if tags are compared.
+                conditional::compares_tags(headers)
+                    .then(|| self.compute_etag(&file, &stat, None, path))
+                    .flatten(),
+                stat.modified().ok(),
+                true,
+            ),
+            Err(_) => (None, None, false),
+        };
+
+        match conditional::evaluate(headers, Method::PUT, exists, etag.as_deref(), last_modified) {
+            Precondition::Proceed => Ok(exists),
+            _ => Err(StatusCode::PreconditionFailed),
+        }
+    }
+
+    /// Replaces a file atomically, stale precompressed sidecars are removed.
+    fn put_file<'b>(
+        &self,
+        uploads: &Uploads,
+        req: &mut Request,
+        path: &str,
+    ) -> Result<Response<'b>, StatusCode> {
+        if path.ends_with('/') {
+            return Err(StatusCode::MethodNotAllowed);
+        }
+
+        if req
+            .headers
+            .content_length()
+            .is_some_and(|len| len > uploads.max_size())
+        {
+            return Err(StatusCode::PayloadTooLarge);
+        }
+
+        let abspath = uploads.resolve_target(&self.static_dir, path, self.symlinks)?;
+        let existed = self.check_write_preconditions(&req.headers, &abspath, path)?;
+
+        upload::write_atomic(&abspath, &mut *req, uploads.max_size()).map_err(|err| {
+            println!("failed to upload {path}: {err}");
+            err.status_code()
+        })?;
+        self.remove_sidecars(path);
+
+        Ok(if existed {
+            Response::new(StatusCode::NoContent)
+        } else {
+            Response::new(StatusCode::Created).with_content_length(0)
+        })
+    }
+
+    /// Removes precompressed sidecars of a path after it's replaced or deleted.
+    fn remove_sidecars(&self, path: &str) {
+        precompressed::remove_sidecars(&self.static_dir, path, self.symlinks, &self.precompressed);
+    }
+
+    /// Removes a file with its precompressed sidecars, directories can't be deleted.
+    fn delete_file<'b>(&self, req: &Request, path: &str) -> Result<Response<'b>, StatusCode> {
+        // Symlinks are removed themselves, not their targets.
+        let abspath = path::resolve_entry(&self.static_dir, path, self.symlinks)
+            .map_err(|e| e.status_code())?;
+        fs::symlink_metadata(&abspath).map_err(|_| StatusCode::NotFound)?;
+        self.check_write_preconditions(&req.headers, &abspath, path)?;
+
+        fs::remove_file(&abspath).map_err(|err| {
+            println!("failed to delete {path}: {err}");
+            upload::UploadError::from(err).status_code()
+        })?;
+        self.remove_sidecars(path);
+        Ok(Response::new(StatusCode::NoContent))
+    }
+}
+
+unsafe impl Send for StaticHandler {}
+unsafe impl Sync for StaticHandler {}
+
+impl Handler for StaticHandler {
+    fn handle_request<'a, 'b>(&self, req: Request<'a>) -> Response<'b> {
+        match req.method {
+            Method::GET => match Self::request_path(&req) {
+                Ok(path) => self.serve_file(&path, &req.headers, req.query_string()),
+                Err(status) => self.error_response(status),
+            },
+            Method::POST => dump_request(req)
+                .unwrap_or_else(|e| Response::string(StatusCode::BadRequest, format!("{}", e))),
+            Method::PUT | Method::DELETE if self.uploads.is_some() => self.handle_write(req),
+            method => {
+                let allow = if self.uploads.is_some() {
+                    "GET, HEAD, POST, PUT, DELETE"
+                } else {
+                    "GET, HEAD, POST"
+                };
+                Problem::new(StatusCode::MethodNotAllowed)
+                    .with_detail(format!("{method} method is not supported"))
+                    .to_response(req.headers.get(HeaderNames::Accept.as_ref()))
+                    .with_header(HeaderNames::Allow.as_ref(), allow)
+            }
+        }
+    }
+
+    fn handle_bad_request(&self, err: &ParseError) -> Response<'_> {
+        Problem::from(err).to_response(None)
+    }
+}
+
+/// Appends content coding to an entity tag, so different encodings never share a tag.
+fn with_encoding_suffix(etag: String, encoding: Option<Encoding>) -> String {
+    match encoding {
+        Some(encoding) => format!("{}-{encoding}\"", etag.trim_end_matches('"')),
+        None => etag,
+    }
+}
+
+fn dump_request<'a, 'b>(mut req: Request<'a>) -> anyhow::Result<Response<'b>> {
+    let len = match req.headers.content_length() {
+        Some(len) if len > MAX_REQUEST_SIZE => {
+            return Ok(Response::string(
+                StatusCode::PayloadTooLarge,
+                "Request entity too large".to_owned(),
+            ))
+        }
+        Some(len) => len,
+        None if req.headers.is_chunked() => 0,
+        None => {
+            return Ok(Response::string(
+                StatusCode::BadRequest,
+                "missing content length".to_owned(),
+            ))
+        }
+    };
+
+    // Right now I can't pass the body itself as a body
+    // because this will cause 2 simultaneous mutable borrows (read + write).
+    let mut body = Vec::with_capacity(len as usize);
+    (&mut req)
+        .take(MAX_REQUEST_SIZE + 1)
+        .read_to_end(&mut body)
+        .with_context(|| "Failed to read request body")?;
+
+    if body.len() as u64 > MAX_REQUEST_SIZE {
+        return Ok(Response::string(
+            StatusCode::PayloadTooLarge,
+            "Request entity too large".to_owned(),
+        ));
+    }
+
+    let content_type = req
+        .headers
+        .get(HeaderNames::ContentType.as_ref())
+        .unwrap_or(mime::DEFAULT_MIME_TYPE);
+
+    Ok(Response::new(StatusCode::OK)
+        .with_content_type(content_type.to_owned())
+        .with_content_length(body.len() as u64)
+        .with_body(body))
+}
@@ -0,0 +1,114 @@
+use super::{listing, StaticHandler};
+use crate::http::{Handler, HeaderNames, ParseError, Problem, Request, Response, StatusCode};
+
+/// Defines how a request path is mapped into a mounted directory.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MountMode {
+    /// Prefix is replaced by the directory, like nginx `alias`:
+    /// `/assets/app.js` is served from `<dir>/app.js`.
+    Alias,
+
+    /// Whole path is appended to the directory, like nginx `root`:
+    /// `/docs/intro.html` is served from `<dir>/docs/intro.html`.
+    Root,
+}
+
+struct Mount {
+    prefix: String,
+    mode: MountMode,
+    handler: StaticHandler,
+}
+
+/// Dispatches requests to static handlers mounted at URL prefixes.
+///
+/// The longest prefix matching whole path segments wins, so `/assets` matches `/assets/app.js`
+/// but not `/assets2`. Each mount keeps its own handler options.
+#[derive(Default)]
+pub struct Mounts {
+    mounts: Vec<Mount>,
+}
+
+impl Mounts {
+    /// Mounts a handler at a prefix using `alias` semantics.
+    pub fn with_alias(self, prefix: &str, handler: StaticHandler) -> Self {
+        self.with_mount(prefix, MountMode::Alias, handler)
+    }
+
+    /// Mounts a handler at a prefix using `root` semantics.
+    pub fn with_root(self, prefix: &str, handler: StaticHandler) -> Self {
+        self.with_mount(prefix, MountMode::Root, handler)
+    }
+
+    pub fn with_mount(mut self, prefix: &str, mode: MountMode, handler: StaticHandler) -> Self {
+        let prefix = prefix.trim_end_matches('/').to_owned();
+        let handler = match mode {
+            // Links generated by the handler should include the stripped prefix.
+            MountMode::Alias => handler.with_url_prefix(&prefix),
+            MountMode::Root => handler,
+        };
+
+        self.mounts.retain(|m| m.prefix != prefix);
+        self.mounts.push(Mount {
+            prefix,
+            mode,
+            handler,
+        });
+        self.mounts
+            .sort_by_key(|m| std::cmp::Reverse(m.prefix.len()));
+        self
+    }
+
+    /// Finds a mount of a request and rewrites its path for the mounted handler.
+    ///
+    /// Prefixes are matched against the decoded and normalized path, so dot-segments and
+    /// escapes can't be used to reach a different mount.
+    fn route<'a>(&self, mut req: Request<'a>) -> (Result<&StaticHandler, StatusCode>, Request<'a>) {
+        let path = match StaticHandler::request_path(&req) {
+            Ok(path) => path,
+            Err(status) => return (Err(status), req),
+        };
+        let Some(mount) = self.mounts.iter().find(|m| {
+            path.strip_prefix(m.prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        }) else {
+            return (Err(StatusCode::NotFound), req);
+        };
+
+        req.url.path = match mount.mode {
+            MountMode::Root => listing::encode_path(&path),
+            MountMode::Alias => match &path[mount.prefix.len()..] {
+                "" => "/".to_owned(),
+                rest => listing::encode_path(rest),
+            },
+        };
+        (Ok(&mount.handler), req)
+    }
+
+    fn error<'b>(status: StatusCode, req: &Request) -> Response<'b> {
+        Problem::new(status).to_response(req.headers.get(HeaderNames::Accept.as_ref()))
+    }
+}
+
+impl Handler for Mounts {
+    fn handle_request<'a, 'b>(&self, req: Request<'a>) -> Response<'b> {
+        match self.route(req) {
+            (Ok(handler), req) => handler.handle_request(req),
+            (Err(status), req) => Self::error(status, &req),
+        }
+    }
+
+    fn handle_head<'a, 'b>(&self, req: Request<'a>) -> Response<'b> {
+        match self.route(req) {
+            (Ok(handler), req) => handler.handle_head(req),
+            (Err(status), req) => Self::error(status, &req),
+        }
+    }
+
+    /// Path of a malformed request is unknown, so the root mount is used if there is one.
+    fn handle_bad_request(&self, err: &ParseError) -> Response<'_> {
+        match self.mounts.iter().find(|m| m.prefix.is_empty()) {
+            Some(mount) => mount.handler.handle_bad_request(err),
+            None => Problem::from(err).to_response(None),
+        }
+    }
+}
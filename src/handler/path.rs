@@ -0,0 +1,210 @@
+use crate::http::StatusCode;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Defines how symbolic links inside the static root are followed.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum SymlinkPolicy {
+    /// Any symlink in a path is rejected.
+    Deny,
+
+    /// Symlinks are followed only if their target is inside the static root.
+    #[default]
+    AllowWithinRoot,
+
+    /// Symlinks are followed anywhere.
+    AllowAll,
+}
+
+/// Path resolution error. Messages never contain filesystem paths.
+#[derive(Error, Debug)]
+pub enum PathError {
+    #[error("Not Found")]
+    NotFound,
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl PathError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::NotFound => StatusCode::NotFound,
+            Self::Forbidden => StatusCode::Forbidden,
+        }
+    }
+}
+
+impl From<io::Error> for PathError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::NotADirectory => Self::NotFound,
+            _ => Self::Forbidden,
+        }
+    }
+}
+
+/// Splits path into segments, resolving dot-segments and skipping empty ones.
+fn segments(path: &str) -> Result<Vec<&str>, PathError> {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop().ok_or(PathError::Forbidden)?;
+            }
+            s => segments.push(s),
+        }
+    }
+    Ok(segments)
+}
+
+/// Normalizes decoded request path by removing dot-segments and duplicate slashes.
+///
+/// Result always starts with `/`, trailing slash is kept. Paths pointing above the root
+/// are forbidden.
+pub fn normalize(path: &str) -> Result<String, PathError> {
+    let segments = segments(path)?;
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if path.ends_with('/') && !segments.is_empty() {
+        normalized.push('/');
+    }
+    Ok(normalized)
+}
+
+/// Resolves request path into a canonical path which is guaranteed to be inside the root.
+///
+/// Root should be a canonical path.
+pub fn resolve(root: &Path, path: &str, symlinks: SymlinkPolicy) -> Result<PathBuf, PathError> {
+    // Path is already percent-decoded, so escapes are taken literally here.
+    if path.contains(['\0', '\\']) {
+        return Err(PathError::Forbidden);
+    }
+
+    // Resolve dot-segments lexically, so path can't point above the root before symlinks are checked.
+    let segments = segments(path)?;
+
+    let mut candidate = root.to_path_buf();
+    for segment in segments {
+        candidate.push(segment);
+        if symlinks == SymlinkPolicy::Deny && fs::symlink_metadata(&candidate)?.is_symlink() {
+            return Err(PathError::Forbidden);
+        }
+    }
+
+    let real_path = fs::canonicalize(&candidate)?;
+    if symlinks != SymlinkPolicy::AllowAll && !real_path.starts_with(root) {
+        return Err(PathError::Forbidden);
+    }
+
+    Ok(real_path)
+}
+
+/// Resolves request path of a file which is going to be created, replaced or removed.
+///
+/// The parent directory is resolved like [resolve], but it must be inside the root even
+/// with [SymlinkPolicy::AllowAll]. The last segment isn't followed, so a symlink is
+/// replaced or removed itself. Missing parent results in [PathError::NotFound].
+pub fn resolve_entry(
+    root: &Path,
+    path: &str,
+    symlinks: SymlinkPolicy,
+) -> Result<PathBuf, PathError> {
+    if path.contains(['\0', '\\']) {
+        return Err(PathError::Forbidden);
+    }
+
+    let mut segments = segments(path)?;
+    let Some(name) = segments.pop() else {
+        return Ok(root.to_path_buf());
+    };
+
+    let symlinks = match symlinks {
+        SymlinkPolicy::AllowAll => SymlinkPolicy::AllowWithinRoot,
+        policy => policy,
+    };
+    let dir = resolve(root, &segments.join("/"), symlinks)?;
+    if !dir.is_dir() {
+        return Err(PathError::NotFound);
+    }
+
+    let entry = dir.join(name);
+    let is_symlink = fs::symlink_metadata(&entry).is_ok_and(|stat| stat.is_symlink());
+    if symlinks == SymlinkPolicy::Deny && is_symlink {
+        return Err(PathError::Forbidden);
+    }
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_dot_segments_and_slashes() {
+        assert_eq!(normalize("").unwrap(), "/");
+        assert_eq!(normalize("/").unwrap(), "/");
+        assert_eq!(normalize("//a/./b//c").unwrap(), "/a/b/c");
+        assert_eq!(normalize("/a/b/../c/").unwrap(), "/a/c/");
+        assert_eq!(normalize("/a/..").unwrap(), "/");
+        assert_eq!(normalize("/a/../").unwrap(), "/");
+    }
+
+    #[test]
+    fn forbids_paths_above_root() {
+        assert!(matches!(normalize("/.."), Err(PathError::Forbidden)));
+        assert!(matches!(normalize("/a/../../b"), Err(PathError::Forbidden)));
+    }
+
+    #[test]
+    fn resolve_rejects_backslashes_and_nul() {
+        let root = std::env::temp_dir();
+        for path in ["/a\\..\\b", "/a\0b"] {
+            assert!(matches!(
+                resolve(&root, path, SymlinkPolicy::Deny),
+                Err(PathError::Forbidden)
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_entry_keeps_parent_inside_root() {
+        use crate::handler::temp_dir::TempDir;
+        use std::os::unix::fs::symlink;
+        let outside = TempDir::new();
+        let root = TempDir::new();
+        fs::create_dir(root.0.join("dir")).unwrap();
+        symlink(&outside.0, root.0.join("out")).unwrap();
+        symlink(root.0.join("dir"), root.0.join("in")).unwrap();
+
+        let entry = |path, symlinks| resolve_entry(&root.0, path, symlinks);
+        assert_eq!(entry("/", SymlinkPolicy::Deny).unwrap(), root.0);
+        assert_eq!(
+            entry("/dir/new", SymlinkPolicy::Deny).unwrap(),
+            root.0.join("dir/new")
+        );
+        assert_eq!(
+            entry("/out", SymlinkPolicy::AllowAll).unwrap(),
+            root.0.join("out")
+        );
+        assert_eq!(
+            entry("/in/new", SymlinkPolicy::AllowAll).unwrap(),
+            root.0.join("dir/new")
+        );
+        assert!(matches!(
+            entry("/out/new", SymlinkPolicy::AllowAll),
+            Err(PathError::Forbidden)
+        ));
+        assert!(matches!(
+            entry("/out", SymlinkPolicy::Deny),
+            Err(PathError::Forbidden)
+        ));
+        assert!(matches!(
+            entry("/missing/new", SymlinkPolicy::AllowAll),
+            Err(PathError::NotFound)
+        ));
+    }
+}
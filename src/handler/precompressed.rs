@@ -0,0 +1,70 @@
+use super::path::{self, SymlinkPolicy};
+use super::StaticFile;
+use crate::http::Encoding;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Result of sidecar lookup.
+pub struct Selection {
+    /// Some sidecars exist, so response depends on `Accept-Encoding`.
+    pub varies: bool,
+
+    /// Best sidecar accepted by a client, e.g. `app.js.br` for `app.js`.
+    pub variant: Option<StaticFile>,
+}
+
+/// Looks up sidecars of a public path and picks one accepted by a client.
+///
+/// Sidecars are resolved the same way as requested files, so they're subject to the same
+/// root and symlink checks.
+pub fn select(
+    root: &Path,
+    path: &str,
+    symlinks: SymlinkPolicy,
+    encodings: &[Encoding],
+    accept_encoding: Option<&str>,
+) -> Selection {
+    let mut sidecars: Vec<(Encoding, PathBuf)> = encodings
+        .iter()
+        .filter_map(|enc| {
+            let ext = enc.extension()?;
+            let abspath = path::resolve(root, &format!("{path}.{ext}"), symlinks).ok()?;
+            abspath.is_file().then_some((*enc, abspath))
+        })
+        .collect();
+
+    let varies = !sidecars.is_empty();
+    let offers: Vec<Encoding> = sidecars.iter().map(|(enc, _)| *enc).collect();
+    let variant = accept_encoding
+        .and_then(|v| Encoding::negotiate(&offers, v))
+        .and_then(|encoding| {
+            let idx = offers.iter().position(|enc| *enc == encoding)?;
+            let (encoding, abspath) = sidecars.swap_remove(idx);
+            let file = fs::File::open(&abspath).ok()?;
+            let stat = file.metadata().ok().filter(|stat| stat.is_file())?;
+            Some(StaticFile {
+                abspath,
+                file,
+                stat,
+                encoding: Some(encoding),
+            })
+        });
+
+    Selection { varies, variant }
+}
+
+/// Removes sidecars of a public path after the original file is replaced or deleted,
+/// so stale content isn't served. Symlinked sidecars are removed themselves.
+pub fn remove_sidecars(root: &Path, path: &str, symlinks: SymlinkPolicy, encodings: &[Encoding]) {
+    for ext in encodings.iter().filter_map(Encoding::extension) {
+        let sidecar = format!("{path}.{ext}");
+        match path::resolve_entry(root, &sidecar, symlinks) {
+            Ok(abspath) if fs::symlink_metadata(&abspath).is_ok_and(|stat| !stat.is_dir()) => {
+                if let Err(err) = fs::remove_file(&abspath) {
+                    println!("failed to remove stale sidecar {sidecar}: {err}");
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::http::{Body, HeaderNames, Response, StatusCode};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Requests with more ranges are served as a whole to avoid excessive file reads.
+const MAX_RANGES: usize = 16;
+
+/// Result of `Range` header evaluation.
+#[derive(Debug, PartialEq)]
+pub enum RangeRequest {
+    /// Range header is missing, ignored or covers the whole file.
+    Full,
+
+    /// Byte ranges to send. End of each range is exclusive.
+    Partial(Vec<Range<u64>>),
+
+    /// None of the requested ranges overlap the file.
+    Unsatisfiable,
+}
+
+/// Parses `Range` header value for a file of `len` bytes.
+///
+/// Malformed headers and units other than `bytes` are ignored as allowed by RFC 9110.
+pub fn parse(header: &str, len: u64) -> RangeRequest {
+    let Some(specs) = header.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+
+    // No range can be satisfied by an empty file.
+    if len == 0 {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((start, end)) = spec.split_once('-') else {
+            return RangeRequest::Full;
+        };
+
+        let range = match (start.trim(), end.trim()) {
+            // Suffix range - last N bytes.
+            ("", suffix) => match parse_position(suffix) {
+                Some(0) => continue,
+                Some(n) => len.saturating_sub(n)..len,
+                None => return RangeRequest::Full,
+            },
+            (start, end) => {
+                let Some(start) = parse_position(start) else {
+                    return RangeRequest::Full;
+                };
+
+                let end = match end {
+                    "" => len,
+                    end => match parse_position(end) {
+                        Some(end) if end >= start => end.saturating_add(1).min(len),
+                        _ => return RangeRequest::Full,
+                    },
+                };
+
+                if start >= len {
+                    continue;
+                }
+                start..end
+            }
+        };
+
+        ranges.push(range);
+    }
+
+    match ranges.len() {
+        0 => RangeRequest::Unsatisfiable,
+        n if n > MAX_RANGES => RangeRequest::Full,
+        1 if ranges[0] == (0..len) => RangeRequest::Full,
+        _ => RangeRequest::Partial(ranges),
+    }
+}
+
+/// Parses byte position, which consists of digits only: `u64::from_str` would accept a sign.
+fn parse_position(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn content_range(range: &Range<u64>, len: u64) -> String {
+    format!("bytes {}-{}/{len}", range.start, range.end - 1)
+}
+
+/// Creates 206 response with a single byte range of a file.
+pub fn single<'a>(file: fs::File, range: Range<u64>, len: u64) -> Response<'a> {
+    Response::new(StatusCode::PartialContent)
+        .with_header(
+            HeaderNames::ContentRange.as_ref(),
+            &content_range(&range, len),
+        )
+        .with_content_length(range.end - range.start)
+        .with_body(Body::file_range(file, range.start, range.end - range.start))
+}
+
+/// Creates 206 response with multiple byte ranges as `multipart/byteranges` body.
+///
+/// Each part is read using a separate file handle, so parts don't share file offset.
+pub fn multipart<'a>(
+    path: &Path,
+    ranges: &[Range<u64>],
+    len: u64,
+    content_type: &str,
+) -> io::Result<Response<'a>> {
+    let boundary = boundary();
+    let mut body: Box<dyn Read> = Box::new(io::empty());
+    let mut body_len = 0;
+    for range in ranges {
+        let header = format!(
+            "\r\n--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: {}\r\n\r\n",
+            content_range(range, len)
+        );
+
+        let mut file = fs::File::open(path)?;
+        file.seek(SeekFrom::Start(range.start))?;
+
+        body_len += header.len() as u64 + (range.end - range.start);
+        body = Box::new(
+            body.chain(Cursor::new(header))
+                .chain(file.take(range.end - range.start)),
+        );
+    }
+
+    let footer = format!("\r\n--{boundary}--\r\n");
+    body_len += footer.len() as u64;
+    body = Box::new(body.chain(Cursor::new(footer)));
+
+    Ok(Response::new(StatusCode::PartialContent)
+        .with_content_type(format!("multipart/byteranges; boundary={boundary}"))
+        .with_content_length(body_len)
+        .with_body(body))
+}
+
+/// Formats `Content-Range` value of 416 response, which tells a client the actual file size.
+pub fn unsatisfied_range(len: u64) -> String {
+    format!("bytes */{len}")
+}
+
+fn boundary() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{nanos:032x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RangeRequest::{Full, Partial, Unsatisfiable};
+
+    fn single_range(start: u64, end: u64) -> RangeRequest {
+        Partial(Vec::from([Range { start, end }]))
+    }
+
+    #[test]
+    fn single_ranges() {
+        assert_eq!(parse("bytes=0-9", 100), single_range(0, 10));
+        assert_eq!(parse("bytes=90-", 100), single_range(90, 100));
+        assert_eq!(parse("bytes=90-200", 100), single_range(90, 100));
+        assert_eq!(parse("bytes=-10", 100), single_range(90, 100));
+        assert_eq!(parse("bytes=-200", 100), Full);
+        assert_eq!(parse("bytes=0-", 100), Full);
+    }
+
+    #[test]
+    fn multiple_ranges() {
+        assert_eq!(parse("bytes=0-0, -1", 100), Partial(vec![0..1, 99..100]));
+        assert_eq!(parse("bytes=0-1,,200-300", 100), single_range(0, 2));
+
+        let many = (0..=MAX_RANGES)
+            .map(|i| format!("{i}-{i}"))
+            .collect::<Vec<_>>();
+        assert_eq!(parse(&format!("bytes={}", many.join(",")), 100), Full);
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        assert_eq!(parse("bytes=100-", 100), Unsatisfiable);
+        assert_eq!(parse("bytes=200-300, -0", 100), Unsatisfiable);
+        assert_eq!(parse("bytes=0-", 0), Unsatisfiable);
+    }
+
+    #[test]
+    fn malformed_headers_are_ignored() {
+        for header in [
+            "items=0-1",
+            "bytes=5",
+            "bytes=5-1",
+            "bytes=a-b",
+            "bytes=+1-2",
+            "bytes=1-+2",
+            "bytes=-+2",
+            "bytes=--2",
+        ] {
+            assert_eq!(parse(header, 100), Full, "{header}");
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::http::accept;
+
+/// Placeholder replaced by the request path in [TryFiles] alternatives.
+const URI: &str = "$uri";
+
+/// Ordered list of files tried when the requested path doesn't exist, similar to nginx `try_files`.
+///
+/// Alternatives (e.g. `$uri.html`) are tried first, then the fallback document.
+#[derive(Clone, Debug, Default)]
+pub struct TryFiles {
+    alternatives: Vec<String>,
+    fallback: Option<String>,
+    html_only: bool,
+}
+
+impl TryFiles {
+    /// Creates typical single-page app setup: `$uri.html`, `$uri/index.html`, then `fallback`
+    /// for requests which accept HTML.
+    pub fn spa(fallback: &str) -> Self {
+        Self::default()
+            .with_alternative("$uri.html")
+            .with_alternative("$uri/index.html")
+            .with_fallback(fallback)
+            .with_html_only(true)
+    }
+
+    /// Adds a path pattern where `$uri` is replaced by the request path.
+    pub fn with_alternative(mut self, pattern: &str) -> Self {
+        self.alternatives.push(pattern.to_owned());
+        self
+    }
+
+    /// Sets document served when nothing else is found, e.g. `/index.html`.
+    pub fn with_fallback(mut self, path: &str) -> Self {
+        self.fallback = Some(path.to_owned());
+        self
+    }
+
+    /// Applies fallback only to requests which explicitly accept HTML,
+    /// so missing assets still get 404.
+    pub fn with_html_only(mut self, html_only: bool) -> Self {
+        self.html_only = html_only;
+        self
+    }
+
+    /// Whether the result depends on `Accept` header.
+    pub fn varies(&self) -> bool {
+        self.fallback.is_some() && self.html_only
+    }
+
+    /// Returns public paths to try in order after the requested path wasn't found.
+    pub fn candidates(&self, path: &str, accept: Option<&str>) -> Vec<String> {
+        let is_dir = path.ends_with('/');
+        let uri = path.trim_end_matches('/');
+        let mut candidates: Vec<String> = self
+            .alternatives
+            .iter()
+            .filter(|pattern| !is_dir || !has_suffix(pattern))
+            .map(|pattern| pattern.replace(URI, uri))
+            .collect();
+
+        let accepts_html = accept.is_some_and(|v| accept::accepts_explicitly(v, "text/html"));
+        match self.fallback {
+            Some(ref fallback) if accepts_html || !self.html_only => {
+                candidates.push(fallback.clone())
+            }
+            _ => {}
+        }
+        candidates
+    }
+}
+
+/// Returns whether a pattern appends something other than a path segment to `$uri`,
+/// e.g. `$uri.html`, which makes no sense for directory paths like `/`.
+fn has_suffix(pattern: &str) -> bool {
+    pattern
+        .match_indices(URI)
+        .any(|(i, _)| !matches!(pattern.as_bytes().get(i + URI.len()), None | Some(b'/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_uri() {
+        let try_files = TryFiles::spa("/index.html");
+        assert_eq!(
+            try_files.candidates("/about", Some("text/html")),
+            ["/about.html", "/about/index.html", "/index.html"]
+        );
+    }
+
+    #[test]
+    fn skips_suffixes_for_directories() {
+        let try_files = TryFiles::spa("/index.html");
+        assert_eq!(try_files.candidates("/", None), ["/index.html"]);
+        assert_eq!(
+            try_files.candidates("/docs/", Some("text/html")),
+            ["/docs/index.html", "/index.html"]
+        );
+    }
+
+    #[test]
+    fn html_only_fallback() {
+        let try_files = TryFiles::spa("/index.html");
+        assert_eq!(
+            try_files.candidates("/app.js", Some("*/*")),
+            ["/app.js.html", "/app.js/index.html"]
+        );
+    }
+}
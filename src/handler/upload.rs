@@ -0,0 +1,388 @@
+use super::cache_control::glob_match;
+use super::path::{self, PathError, SymlinkPolicy};
+use crate::http::{HeaderNames, Request, StatusCode};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use thiserror::Error;
+
+const DEFAULT_MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Counter making names of temporary files unique within the process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Decides whether a request may modify a file with a public path.
+type Authorizer = Box<dyn Fn(&Request, &str) -> bool + Send + Sync>;
+
+/// Write access to the static root: `PUT` creates or replaces files, `DELETE` removes them.
+///
+/// Every request is checked by the authorisation hook, which denies everything until
+/// it's set by [Uploads::with_authorizer] or [Uploads::with_bearer_token].
+pub struct Uploads {
+    max_size: u64,
+    allowed: Vec<String>,
+    create_dirs: bool,
+    authorize: Authorizer,
+    challenge: String,
+}
+
+impl Default for Uploads {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+            allowed: Vec::new(),
+            create_dirs: false,
+            authorize: Box::new(|_, _| false),
+            challenge: "Bearer".to_owned(),
+        }
+    }
+}
+
+impl Uploads {
+    /// Sets the largest accepted file size in bytes.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Allows writes to paths matching a glob pattern, e.g. `/artifacts/**`.
+    ///
+    /// Patterns use the same syntax as [super::CacheControl] rules. Without patterns
+    /// any path is allowed.
+    pub fn with_allowed_path(mut self, pattern: &str) -> Self {
+        self.allowed.push(pattern.to_owned());
+        self
+    }
+
+    /// Creates missing parent directories of uploaded files, otherwise they're rejected with 409.
+    pub fn with_create_dirs(mut self, create_dirs: bool) -> Self {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    /// Sets authorisation hook called with a request and its normalized path.
+    ///
+    /// `challenge` is sent in `WWW-Authenticate` header when credentials are missing.
+    pub fn with_authorizer(
+        mut self,
+        challenge: &str,
+        authorize: impl Fn(&Request, &str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.challenge = challenge.to_owned();
+        self.authorize = Box::new(authorize);
+        self
+    }
+
+    /// Allows requests with `Authorization: Bearer <token>` header.
+    pub fn with_bearer_token(self, token: &str) -> Self {
+        let expected = format!("Bearer {token}");
+        self.with_authorizer("Bearer", move |req, _| {
+            req.headers
+                .get(HeaderNames::Authorization.as_ref())
+                .is_some_and(|value| constant_time_eq(value.trim(), &expected))
+        })
+    }
+
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    pub fn challenge(&self) -> &str {
+        &self.challenge
+    }
+
+    pub fn authorize(&self, req: &Request, path: &str) -> bool {
+        (self.authorize)(req, path)
+    }
+
+    /// Returns whether writes to a path are allowed by the patterns.
+    pub fn allows(&self, path: &str) -> bool {
+        self.allowed.is_empty()
+            || self
+                .allowed
+                .iter()
+                .any(|pattern| glob_match(pattern.as_bytes(), path.as_bytes()))
+    }
+
+    /// Resolves an upload target, which may not exist yet.
+    ///
+    /// The parent directory must exist inside the root, missing ones are created
+    /// if enabled. A symlink at the target is replaced itself, never written through.
+    pub fn resolve_target(
+        &self,
+        root: &Path,
+        path: &str,
+        symlinks: SymlinkPolicy,
+    ) -> Result<PathBuf, StatusCode> {
+        let resolve = || path::resolve_entry(root, path, symlinks);
+        let abspath = match resolve() {
+            Err(PathError::NotFound) if self.create_dirs => {
+                let (parent, _) = path.rsplit_once('/').unwrap_or(("", path));
+                create_dirs(root, parent, symlinks)?;
+                resolve()
+            }
+            result => result,
+        };
+        abspath.map_err(|err| match err {
+            PathError::NotFound => StatusCode::Conflict,
+            err => err.status_code(),
+        })
+    }
+}
+
+/// Error of writing an uploaded file.
+#[derive(Error, Debug)]
+pub enum UploadError {
+    #[error("file is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl UploadError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::TooLarge(_) => StatusCode::PayloadTooLarge,
+            Self::Io(err) => match err.kind() {
+                io::ErrorKind::PermissionDenied => StatusCode::Forbidden,
+                io::ErrorKind::StorageFull => StatusCode::InsufficientStorage,
+                _ => StatusCode::InternalServerError,
+            },
+        }
+    }
+}
+
+/// Writes a body into a temporary file next to the target, then renames it over the target.
+///
+/// Readers see either the old or the new file, never a partial upload.
+pub fn write_atomic(target: &Path, body: impl Read, max_size: u64) -> Result<u64, UploadError> {
+    let temp = temp_path(target);
+    let write = || {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        let len = io::copy(&mut body.take(max_size.saturating_add(1)), &mut file)?;
+        if len > max_size {
+            return Err(UploadError::TooLarge(max_size));
+        }
+
+        file.sync_all()?;
+        fs::rename(&temp, target)?;
+        Ok(len)
+    };
+
+    let result = write();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Returns unique hidden path next to a target, so it can be renamed over the target.
+pub fn temp_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    target.with_file_name(format!(
+        ".{name}.{}-{}.upload",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Creates missing directories of a public path one by one, so each stays inside the root.
+fn create_dirs(root: &Path, path: &str, symlinks: SymlinkPolicy) -> Result<(), StatusCode> {
+    let mut public = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        public.push('/');
+        public.push_str(segment);
+        let dir = path::resolve_entry(root, &public, symlinks).map_err(|err| match err {
+            PathError::NotFound => StatusCode::Conflict,
+            err => err.status_code(),
+        })?;
+        match fs::create_dir(&dir) {
+            Err(err) if err.kind() != io::ErrorKind::AlreadyExists => {
+                println!("failed to create directory {public}: {err}");
+                return Err(StatusCode::InternalServerError);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Compares secrets in time independent of the position of the first difference.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::temp_dir::TempDir;
+    use crate::handler::StaticHandler;
+    use crate::http::{Handler, Response};
+
+    #[test]
+    fn write_atomic_replaces_target() {
+        let dir = TempDir::new();
+        let target = dir.0.join("file");
+        fs::write(&target, "old").unwrap();
+
+        assert_eq!(write_atomic(&target, &b"new"[..], 3).unwrap(), 3);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(dir.names(), ["file"]);
+    }
+
+    #[test]
+    fn write_atomic_enforces_limit() {
+        let dir = TempDir::new();
+        let target = dir.0.join("file");
+        fs::write(&target, "old").unwrap();
+
+        let err = write_atomic(&target, &b"toolong"[..], 3).unwrap_err();
+        assert!(matches!(err, UploadError::TooLarge(3)));
+        assert!(matches!(err.status_code(), StatusCode::PayloadTooLarge));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        assert_eq!(dir.names(), ["file"]);
+    }
+
+    #[test]
+    fn write_atomic_fails_without_parent() {
+        let dir = TempDir::new();
+        let err = write_atomic(&dir.0.join("missing/file"), &b""[..], 3).unwrap_err();
+        assert!(matches!(err, UploadError::Io(_)));
+    }
+
+    #[test]
+    fn secrets_comparison() {
+        assert!(constant_time_eq("Bearer abc", "Bearer abc"));
+        assert!(!constant_time_eq("Bearer abd", "Bearer abc"));
+        assert!(!constant_time_eq("Bearer ab", "Bearer abc"));
+        assert!(constant_time_eq("", ""));
+    }
+
+    #[test]
+    fn allowed_paths() {
+        assert!(Uploads::default().allows("/anything"));
+
+        let uploads = Uploads::default()
+            .with_allowed_path("/artifacts/**")
+            .with_allowed_path("/*.txt");
+        assert!(uploads.allows("/artifacts/a/b.zip"));
+        assert!(uploads.allows("/notes.txt"));
+        assert!(!uploads.allows("/docs/notes.txt"));
+        assert!(!uploads.allows("/index.html"));
+    }
+
+    #[test]
+    fn resolve_target_checks_parent() {
+        let dir = TempDir::new();
+        let symlinks = SymlinkPolicy::default();
+        let uploads = Uploads::default();
+
+        assert_eq!(
+            uploads.resolve_target(&dir.0, "/new.txt", symlinks).ok(),
+            Some(dir.0.join("new.txt"))
+        );
+        assert!(matches!(
+            uploads.resolve_target(&dir.0, "/a/b/new.txt", symlinks),
+            Err(StatusCode::Conflict)
+        ));
+
+        let uploads = uploads.with_create_dirs(true);
+        assert_eq!(
+            uploads
+                .resolve_target(&dir.0, "/a/b/new.txt", symlinks)
+                .ok(),
+            Some(dir.0.join("a/b/new.txt"))
+        );
+        assert!(dir.0.join("a/b").is_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn put_replaces_symlinks_themselves() {
+        use std::os::unix::fs::symlink;
+        let outside = TempDir::new();
+        fs::write(outside.0.join("file.txt"), "outside").unwrap();
+        fs::create_dir(outside.0.join("dir")).unwrap();
+
+        let dir = TempDir::new();
+        symlink(outside.0.join("file.txt"), dir.0.join("file.txt")).unwrap();
+        symlink(outside.0.join("dir"), dir.0.join("dir")).unwrap();
+        let uploads = Uploads::default().with_bearer_token("t");
+        let handler = StaticHandler::new(dir.0.display().to_string())
+            .with_symlinks(SymlinkPolicy::AllowAll)
+            .with_uploads(uploads);
+
+        let rsp = put(&handler, "Authorization: Bearer t\r\n", "new");
+        assert_eq!(rsp.status_code as u16, 204);
+        assert_eq!(
+            fs::read_to_string(outside.0.join("file.txt")).unwrap(),
+            "outside"
+        );
+        assert!(!fs::symlink_metadata(dir.0.join("file.txt"))
+            .unwrap()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(dir.0.join("file.txt")).unwrap(), "new");
+
+        assert!(matches!(
+            handler.uploads.as_ref().unwrap().resolve_target(
+                &dir.0,
+                "/dir/file.txt",
+                SymlinkPolicy::AllowAll
+            ),
+            Err(StatusCode::Forbidden)
+        ));
+    }
+
+    fn put(handler: &StaticHandler, headers: &str, body: &str) -> Response<'static> {
+        let raw = format!(
+            "PUT /file.txt HTTP/1.1\r\nContent-Length: {}\r\n{headers}\r\n{body}",
+            body.len()
+        );
+        let mut src = raw.as_bytes();
+        handler.handle_request(Request::from_reader(&mut src).unwrap())
+    }
+
+    #[test]
+    fn put_with_preconditions() {
+        let dir = TempDir::new();
+        let uploads = Uploads::default().with_bearer_token("t");
+        let handler = StaticHandler::new(dir.0.display().to_string()).with_uploads(uploads);
+        let auth = "Authorization: Bearer t\r\n";
+        let status = |rsp: Response| rsp.status_code as u16;
+
+        assert_eq!(status(put(&handler, "", "a")), 401);
+        assert_eq!(
+            status(put(&handler, &format!("{auth}If-Match: *\r\n"), "a")),
+            412
+        );
+        assert_eq!(status(put(&handler, auth, "a")), 201);
+        assert_eq!(
+            status(put(&handler, &format!("{auth}If-Match: *\r\n"), "b")),
+            204
+        );
+        assert_eq!(
+            status(put(&handler, &format!("{auth}If-None-Match: *\r\n"), "c")),
+            412
+        );
+        assert_eq!(
+            status(put(
+                &handler,
+                &format!("{auth}If-Match: \"stale\"\r\n"),
+                "c"
+            )),
+            412
+        );
+        assert_eq!(fs::read_to_string(dir.0.join("file.txt")).unwrap(), "b");
+    }
+}
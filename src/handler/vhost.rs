@@ -0,0 +1,100 @@
+use crate::http::{Handler, HeaderNames, ParseError, Problem, Request, Response, StatusCode};
+use std::collections::HashMap;
+
+/// Dispatches requests to handlers by `Host` header.
+///
+/// Host is matched in order: exact name, the most specific wildcard (`*.example.com` matches
+/// any subdomain, but not `example.com` itself), then the default handler.
+/// Names are compared case-insensitively and without port.
+#[derive(Default)]
+pub struct VirtualHosts {
+    exact: HashMap<String, Box<dyn Handler>>,
+    wildcards: Vec<(String, Box<dyn Handler>)>,
+    default: Option<Box<dyn Handler>>,
+}
+
+impl VirtualHosts {
+    /// Adds a handler of a host name or a `*.` wildcard pattern.
+    pub fn with_host(mut self, name: &str, handler: impl Handler + 'static) -> Self {
+        let name = normalize_host(name);
+        match name.strip_prefix("*.") {
+            Some(suffix) => {
+                self.wildcards
+                    .push((format!(".{suffix}"), Box::new(handler)));
+                // The longest suffix is checked first.
+                self.wildcards
+                    .sort_by_key(|(suffix, _)| std::cmp::Reverse(suffix.len()));
+            }
+            None => {
+                self.exact.insert(name, Box::new(handler));
+            }
+        }
+        self
+    }
+
+    /// Sets a handler of requests which don't match any host.
+    pub fn with_default(mut self, handler: impl Handler + 'static) -> Self {
+        self.default = Some(Box::new(handler));
+        self
+    }
+
+    fn find(&self, req: &Request) -> Option<&dyn Handler> {
+        let host = req
+            .headers
+            .get(HeaderNames::Host.as_ref())
+            .map(normalize_host);
+
+        let matched = host.and_then(|host| {
+            self.exact.get(&host).or_else(|| {
+                self.wildcards
+                    .iter()
+                    .find(|(suffix, _)| host.ends_with(suffix.as_str()))
+                    .map(|(_, handler)| handler)
+            })
+        });
+
+        matched.or(self.default.as_ref()).map(|h| h.as_ref())
+    }
+
+    fn misdirected<'b>(req: &Request) -> Response<'b> {
+        Problem::new(StatusCode::MisdirectedRequest)
+            .with_detail("Host is not served by this server")
+            .to_response(req.headers.get(HeaderNames::Accept.as_ref()))
+    }
+}
+
+impl Handler for VirtualHosts {
+    fn handle_request<'a, 'b>(&self, req: Request<'a>) -> Response<'b> {
+        match self.find(&req) {
+            Some(handler) => handler.handle_request(req),
+            None => Self::misdirected(&req),
+        }
+    }
+
+    fn handle_head<'a, 'b>(&self, req: Request<'a>) -> Response<'b> {
+        match self.find(&req) {
+            Some(handler) => handler.handle_head(req),
+            None => Self::misdirected(&req),
+        }
+    }
+
+    /// Host of a malformed request is unknown, so the default handler is used.
+    fn handle_bad_request(&self, err: &ParseError) -> Response<'_> {
+        match self.default {
+            Some(ref handler) => handler.handle_bad_request(err),
+            None => Problem::from(err).to_response(None),
+        }
+    }
+}
+
+/// Lowercases host name and strips port and trailing dot.
+fn normalize_host(host: &str) -> String {
+    let host = host.trim();
+    let name = match host.strip_prefix('[') {
+        // IPv6 literal, e.g. `[::1]:8080`.
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
@@ -0,0 +1,1242 @@
+use super::listing;
+use super::lock::{self, Lock, Locks};
+use super::mime::DEFAULT_MIME_TYPE;
+use super::path::{self, PathError};
+use super::{upload, StaticHandler, MAX_REQUEST_SIZE};
+use crate::http::{
+    self, escape, Handler, HeaderNames, Method, ParseError, Request, Response, StatusCode,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAV_NS: &str = "DAV:";
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+const ALLOWED_METHODS: &str =
+    "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, PROPPATCH, MKCOL, COPY, MOVE, LOCK, UNLOCK";
+
+/// Properties computed from the file system, they can't be changed by `PROPPATCH`.
+const LIVE_PROPS: &[&str] = &[
+    "creationdate",
+    "displayname",
+    "getcontentlength",
+    "getcontenttype",
+    "getetag",
+    "getlastmodified",
+    "lockdiscovery",
+    "resourcetype",
+    "supportedlock",
+];
+
+const SUPPORTED_LOCK: &str = "<D:lockentry><D:lockscope><D:exclusive/></D:lockscope>\
+<D:locktype><D:write/></D:locktype></D:lockentry>\
+<D:lockentry><D:lockscope><D:shared/></D:lockscope>\
+<D:locktype><D:write/></D:locktype></D:lockentry>";
+
+/// Namespace and local name of a property.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct PropName {
+    ns: String,
+    name: String,
+}
+
+impl PropName {
+    fn of(node: roxmltree::Node) -> Self {
+        let tag = node.tag_name();
+        Self {
+            ns: tag.namespace().unwrap_or_default().to_owned(),
+            name: tag.name().to_owned(),
+        }
+    }
+
+    fn dav(name: &str) -> Self {
+        Self {
+            ns: DAV_NS.to_owned(),
+            name: name.to_owned(),
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        self.ns == DAV_NS && LIVE_PROPS.contains(&self.name.as_str())
+    }
+
+    /// Renders property element with a value given as an XML fragment.
+    fn element(&self, value: &str) -> String {
+        let (tag, decl) = if self.ns == DAV_NS {
+            (format!("D:{}", self.name), String::new())
+        } else if self.ns.is_empty() {
+            (self.name.clone(), " xmlns=\"\"".to_owned())
+        } else {
+            (
+                format!("P:{}", self.name),
+                format!(" xmlns:P=\"{}\"", escape::html(&self.ns)),
+            )
+        };
+
+        if value.is_empty() {
+            format!("<{tag}{decl}/>")
+        } else {
+            format!("<{tag}{decl}>{value}</{tag}>")
+        }
+    }
+}
+
+/// Dead properties of a resource with values stored as XML fragments.
+type Props = BTreeMap<PropName, String>;
+
+/// Properties requested by `PROPFIND`.
+enum PropFind {
+    AllProp,
+    PropName,
+    Prop(Vec<PropName>),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Depth {
+    Zero,
+    One,
+    Infinity,
+}
+
+/// Request target mapped into the static root.
+struct Resource {
+    /// Normalized public path without trailing slash, `/` for the root.
+    path: String,
+    abspath: PathBuf,
+
+    /// Metadata of an existing resource, `None` if the path is unmapped.
+    stat: Option<fs::Metadata>,
+}
+
+impl Resource {
+    fn is_dir(&self) -> bool {
+        self.stat.as_ref().is_some_and(|s| s.is_dir())
+    }
+}
+
+/// WebDAV (RFC 4918) class 1 and 2 server on top of a static handler.
+///
+/// `GET`, `HEAD` and `POST` are served by the static handler, other methods edit files
+/// in its root. Dead properties and locks are kept in memory and lost on restart.
+///
+/// Methods which modify the tree are authorised by [super::Uploads] of the static handler,
+/// like its own `PUT` and `DELETE`. Without [StaticHandler::with_uploads] the tree is read-only.
+pub struct WebDav {
+    handler: StaticHandler,
+    props: Mutex<HashMap<PathBuf, Props>>,
+    locks: Locks,
+}
+
+impl WebDav {
+    pub fn new(handler: StaticHandler) -> Self {
+        Self {
+            handler,
+            props: Mutex::new(HashMap::new()),
+            locks: Locks::default(),
+        }
+    }
+
+    /// Checks that a client may modify the request target, and the destination of `COPY`
+    /// and `MOVE`. A copied source is only read, so it's not checked.
+    fn authorize(&self, req: &Request) -> Result<(), StatusCode> {
+        if req.method != Method::COPY {
+            self.handler
+                .authorize_write(req, &Self::request_path(req)?)?;
+        }
+        if matches!(req.method, Method::COPY | Method::MOVE) {
+            self.handler.authorize_write(req, &self.destination(req)?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns decoded and normalized request path.
+    fn request_path(req: &Request) -> Result<String, StatusCode> {
+        let path = http::decode_path(req.path()).map_err(|_| StatusCode::BadRequest)?;
+        path::normalize(&path).map_err(|err| err.status_code())
+    }
+
+    /// Maps a normalized path into the static root.
+    ///
+    /// Unmapped paths are allowed only inside existing collections, otherwise the request
+    /// conflicts with the current state of the tree.
+    fn locate(&self, path: &str) -> Result<Resource, StatusCode> {
+        let root = &self.handler.static_dir;
+        let symlinks = self.handler.symlinks;
+        let trimmed = path.trim_end_matches('/');
+        let resource = |abspath, stat| Resource {
+            path: if trimmed.is_empty() { "/" } else { trimmed }.to_owned(),
+            abspath,
+            stat,
+        };
+
+        match path::resolve(root, path, symlinks) {
+            Ok(abspath) => {
+                let stat = fs::metadata(&abspath).map_err(|_| StatusCode::Forbidden)?;
+                return Ok(resource(abspath, Some(stat)));
+            }
+            Err(PathError::NotFound) => {}
+            Err(err) => return Err(err.status_code()),
+        }
+
+        let abspath = match path::resolve_entry(root, path, symlinks) {
+            Ok(abspath) => abspath,
+            Err(PathError::NotFound) => return Err(StatusCode::Conflict),
+            Err(err) => return Err(err.status_code()),
+        };
+        // A dangling symlink isn't unmapped, but it can't be read either.
+        if fs::symlink_metadata(&abspath).is_ok() {
+            return Err(StatusCode::Forbidden);
+        }
+        Ok(resource(abspath, None))
+    }
+
+    /// Maps a normalized path into a directory entry which is going to be replaced or removed.
+    ///
+    /// Unlike [WebDav::locate], symlinks aren't followed, so they're moved or removed
+    /// themselves rather than their targets.
+    fn locate_entry(&self, path: &str) -> Result<Resource, StatusCode> {
+        let root = &self.handler.static_dir;
+        let abspath = match path::resolve_entry(root, path, self.handler.symlinks) {
+            Ok(abspath) => abspath,
+            Err(PathError::NotFound) => return Err(StatusCode::Conflict),
+            Err(err) => return Err(err.status_code()),
+        };
+
+        let trimmed = path.trim_end_matches('/');
+        Ok(Resource {
+            path: if trimmed.is_empty() { "/" } else { trimmed }.to_owned(),
+            stat: fs::symlink_metadata(&abspath).ok(),
+            abspath,
+        })
+    }
+
+    /// Returns an existing resource, unmapped paths are not found.
+    fn locate_existing(&self, path: &str) -> Result<Resource, StatusCode> {
+        match self.locate(path) {
+            Ok(resource) if resource.stat.is_some() => Ok(resource),
+            Ok(_) | Err(StatusCode::Conflict) => Err(StatusCode::NotFound),
+            Err(status) => Err(status),
+        }
+    }
+
+    /// Returns an existing directory entry, unmapped paths are not found.
+    fn locate_existing_entry(&self, path: &str) -> Result<Resource, StatusCode> {
+        match self.locate_entry(path) {
+            Ok(resource) if resource.stat.is_some() => Ok(resource),
+            Ok(_) | Err(StatusCode::Conflict) => Err(StatusCode::NotFound),
+            Err(status) => Err(status),
+        }
+    }
+
+    /// Returns public URL of a resource, collections end with a slash.
+    fn href(&self, path: &str, is_dir: bool) -> String {
+        let mut href = format!("{}{path}", self.handler.url_prefix);
+        if is_dir && !href.ends_with('/') {
+            href.push('/');
+        }
+        escape::html(&listing::encode_path(&href))
+    }
+
+    /// Returns lock tokens submitted in `If` header.
+    ///
+    /// Only lock tokens are evaluated: a request listing tokens which don't identify any
+    /// active lock fails, entity tags are ignored.
+    fn submitted_tokens(&self, req: &Request) -> Result<Vec<String>, StatusCode> {
+        let Some(value) = req.headers.get(HeaderNames::If.as_ref()) else {
+            return Ok(Vec::new());
+        };
+
+        let tokens: Vec<String> = lock::if_tokens(value)
+            .into_iter()
+            .filter(|t| t.starts_with("opaquelocktoken:"))
+            .collect();
+        if !tokens.is_empty() && !tokens.iter().any(|t| self.locks.exists(t)) {
+            return Err(StatusCode::PreconditionFailed);
+        }
+        Ok(tokens)
+    }
+
+    /// Checks that a client may modify a resource, members are checked if `recursive` is set.
+    fn check_locks(&self, req: &Request, path: &str, recursive: bool) -> Result<(), StatusCode> {
+        let tokens = self.submitted_tokens(req)?;
+        if self.locks.permits(path, &tokens, recursive) {
+            Ok(())
+        } else {
+            Err(StatusCode::Locked)
+        }
+    }
+
+    fn options<'b>(&self) -> Response<'b> {
+        Response::new(StatusCode::OK)
+            .with_content_length(0)
+            .with_header(HeaderNames::DAV.as_ref(), "1, 2")
+            .with_header(HeaderNames::Allow.as_ref(), ALLOWED_METHODS)
+            .with_header("MS-Author-Via", "DAV")
+    }
+
+    fn propfind<'b>(&self, req: &mut Request) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        let depth = depth(req, Depth::Infinity)?;
+        if depth == Depth::Infinity {
+            return Ok(dav_error(
+                StatusCode::Forbidden,
+                "<D:propfind-finite-depth/>",
+            ));
+        }
+
+        let query = parse_propfind(&read_body(req)?)?;
+        let resource = self.locate_existing(&path)?;
+
+        let mut out = format!("{XML_DECLARATION}<D:multistatus xmlns:D=\"DAV:\">");
+        self.write_props(&mut out, &resource, &query);
+        if depth == Depth::One && resource.is_dir() {
+            for member in self.members(&resource) {
+                self.write_props(&mut out, &member, &query);
+            }
+        }
+        out.push_str("</D:multistatus>");
+        Ok(multistatus(out))
+    }
+
+    /// Returns members of a collection which can be served according to the symlink policy.
+    fn members(&self, collection: &Resource) -> Vec<Resource> {
+        let Ok(entries) = fs::read_dir(&collection.abspath) else {
+            return Vec::new();
+        };
+
+        let base = collection.path.trim_end_matches('/');
+        let mut names: Vec<String> = entries
+            .filter_map(|e| e.ok()?.file_name().into_string().ok())
+            .collect();
+        names.sort();
+        names
+            .iter()
+            .filter_map(|name| self.locate_existing(&format!("{base}/{name}")).ok())
+            .collect()
+    }
+
+    /// Writes `DAV:response` element with requested properties of a resource.
+    fn write_props(&self, out: &mut String, resource: &Resource, query: &PropFind) {
+        let dead = self
+            .props
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&resource.abspath)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut found = String::new();
+        let mut missing = String::new();
+        match query {
+            PropFind::AllProp | PropFind::PropName => {
+                let names_only = matches!(query, PropFind::PropName);
+                for name in LIVE_PROPS {
+                    if let Some(value) = self.live_prop(name, resource) {
+                        let value = if names_only { "" } else { &value };
+                        found.push_str(&PropName::dav(name).element(value));
+                    }
+                }
+                for (name, value) in &dead {
+                    found.push_str(&name.element(if names_only { "" } else { value }));
+                }
+            }
+            PropFind::Prop(names) => {
+                for name in names {
+                    let value = if name.is_live() {
+                        self.live_prop(&name.name, resource)
+                    } else {
+                        dead.get(name).cloned()
+                    };
+
+                    match value {
+                        Some(value) => found.push_str(&name.element(&value)),
+                        None => missing.push_str(&name.element("")),
+                    }
+                }
+            }
+        }
+
+        let _ = write!(
+            out,
+            "<D:response><D:href>{}</D:href>",
+            self.href(&resource.path, resource.is_dir())
+        );
+        if !found.is_empty() || missing.is_empty() {
+            write_propstat(out, &found, StatusCode::OK);
+        }
+        if !missing.is_empty() {
+            write_propstat(out, &missing, StatusCode::NotFound);
+        }
+        out.push_str("</D:response>");
+    }
+
+    /// Returns value of a live property as an XML fragment, `None` if the resource lacks it.
+    fn live_prop(&self, name: &str, resource: &Resource) -> Option<String> {
+        let stat = resource.stat.as_ref()?;
+        let is_file = !stat.is_dir();
+        match name {
+            "creationdate" => stat.created().ok().map(format_rfc3339),
+            "displayname" => {
+                let name = resource.path.rsplit('/').next().unwrap_or_default();
+                Some(escape::html(name))
+            }
+            "getcontentlength" if is_file => Some(stat.len().to_string()),
+            "getcontenttype" if is_file => {
+                let mime = self.handler.mime_types.lookup(&resource.path);
+                Some(escape::html(mime.unwrap_or(DEFAULT_MIME_TYPE)))
+            }
+            "getetag" if is_file => {
+                let file = fs::File::open(&resource.abspath).ok()?;
+                self.handler
+                    .compute_etag(&file, stat, None, &resource.path)
+                    .map(|etag| escape::html(&etag))
+            }
+            "getlastmodified" => stat
+                .modified()
+                .ok()
+                .map(|t| escape::html(&httpdate::fmt_http_date(t))),
+            "resourcetype" if is_file => Some(String::new()),
+            "resourcetype" => Some("<D:collection/>".to_owned()),
+            "supportedlock" => Some(SUPPORTED_LOCK.to_owned()),
+            "lockdiscovery" => Some(
+                self.locks
+                    .discover(&resource.path)
+                    .iter()
+                    .map(|lock| self.active_lock(lock))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Renders `DAV:activelock` element of a lock.
+    fn active_lock(&self, lock: &Lock) -> String {
+        format!(
+            "<D:activelock><D:locktype><D:write/></D:locktype>\
+<D:lockscope><D:{}/></D:lockscope><D:depth>{}</D:depth>{}\
+<D:timeout>Second-{}</D:timeout><D:locktoken><D:href>{}</D:href></D:locktoken>\
+<D:lockroot><D:href>{}</D:href></D:lockroot></D:activelock>",
+            if lock.exclusive {
+                "exclusive"
+            } else {
+                "shared"
+            },
+            if lock.recursive { "infinity" } else { "0" },
+            lock.owner
+                .as_ref()
+                .map(|owner| format!("<D:owner>{owner}</D:owner>"))
+                .unwrap_or_default(),
+            lock.timeout.as_secs(),
+            escape::html(&lock.token),
+            self.href(&lock.path, false),
+        )
+    }
+
+    fn proppatch<'b>(&self, req: &mut Request) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        let body = read_body(req)?;
+        let doc = roxmltree::Document::parse(&body).map_err(|_| StatusCode::BadRequest)?;
+        let root = doc.root_element();
+        if !is_dav(root, "propertyupdate") {
+            return Err(StatusCode::BadRequest);
+        }
+
+        // Instructions are applied in document order, `None` value removes a property.
+        let mut updates: Vec<(PropName, Option<String>)> = Vec::new();
+        for instruction in root.children().filter(|n| n.is_element()) {
+            let set = is_dav(instruction, "set");
+            if !set && !is_dav(instruction, "remove") {
+                return Err(StatusCode::BadRequest);
+            }
+
+            let props = instruction.children().filter(|n| is_dav(*n, "prop"));
+            for prop in props.flat_map(|p| p.children().filter(|n| n.is_element())) {
+                let value = if set { Some(inner_xml(prop)) } else { None };
+                updates.push((PropName::of(prop), value));
+            }
+        }
+
+        let resource = self.locate_existing(&path)?;
+        self.check_locks(req, &path, false)?;
+
+        // Changes are atomic: if a live property can't be changed, nothing is.
+        let protected = updates.iter().any(|(name, _)| name.is_live());
+        if !protected {
+            let mut props = self.props.lock().unwrap_or_else(|e| e.into_inner());
+            let dead = props.entry(resource.abspath.clone()).or_default();
+            for (name, value) in &updates {
+                match value {
+                    Some(value) => dead.insert(name.clone(), value.clone()),
+                    None => dead.remove(name),
+                };
+            }
+        }
+
+        let mut out = format!(
+            "{XML_DECLARATION}<D:multistatus xmlns:D=\"DAV:\"><D:response><D:href>{}</D:href>",
+            self.href(&resource.path, resource.is_dir())
+        );
+        let statuses = if protected {
+            [StatusCode::Forbidden, StatusCode::FailedDependency]
+        } else {
+            [StatusCode::OK, StatusCode::OK]
+        };
+        let (live, dead): (Vec<_>, Vec<_>) = updates.iter().partition(|(name, _)| name.is_live());
+        for (status, group) in statuses.into_iter().zip([live, dead]) {
+            let props: String = group.iter().map(|(name, _)| name.element("")).collect();
+            if !props.is_empty() {
+                write_propstat(&mut out, &props, status);
+            }
+        }
+        out.push_str("</D:response></D:multistatus>");
+        Ok(multistatus(out))
+    }
+
+    fn mkcol<'b>(&self, req: &mut Request) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        if !read_body(req)?.is_empty() {
+            return Err(StatusCode::UnsupportedMediaType);
+        }
+
+        let resource = self.locate(&path)?;
+        if resource.stat.is_some() {
+            return Err(StatusCode::MethodNotAllowed);
+        }
+
+        self.check_locks(req, &path, false)?;
+        fs::create_dir(&resource.abspath).map_err(|err| io_status(&path, err))?;
+        Ok(Response::new(StatusCode::Created).with_content_length(0))
+    }
+
+    /// Writes a file the same way as [StaticHandler] uploads, after lock checks.
+    fn put<'b>(&self, req: &mut Request) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        self.check_locks(req, &path, false)?;
+        let uploads = self.handler.uploads.as_ref().ok_or(StatusCode::Forbidden)?;
+        self.handler.put_file(uploads, req, &path)
+    }
+
+    fn delete<'b>(&self, req: &Request) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        let resource = self.locate_existing_entry(&path)?;
+        if resource.path == "/" {
+            return Err(StatusCode::Forbidden);
+        }
+
+        self.check_locks(req, &path, true)?;
+        self.remove(&resource)?;
+        self.handler.remove_sidecars(&resource.path);
+        Ok(Response::new(StatusCode::NoContent))
+    }
+
+    /// Removes a resource with its members, properties and locks.
+    fn remove(&self, resource: &Resource) -> Result<(), StatusCode> {
+        remove_tree(&resource.abspath).map_err(|err| io_status(&resource.path, err))?;
+
+        self.transfer_props(&resource.abspath, None);
+        self.locks.remove_tree(&resource.path);
+        Ok(())
+    }
+
+    /// Copies dead properties of a tree to another location, or drops them if `to` is `None`.
+    ///
+    /// Properties of the source are removed unless it's a copy.
+    fn transfer_props(&self, from: &Path, to: Option<(&Path, bool)>) {
+        let mut props = self.props.lock().unwrap_or_else(|e| e.into_inner());
+        let tree: Vec<(PathBuf, Props)> = props
+            .iter()
+            .filter(|(path, _)| path.starts_with(from))
+            .map(|(path, p)| (path.clone(), p.clone()))
+            .collect();
+
+        let keep_source = matches!(to, Some((_, true)));
+        if !keep_source {
+            props.retain(|path, _| !path.starts_with(from));
+        }
+
+        if let Some((to, _)) = to {
+            for (path, p) in tree {
+                let rest = path.strip_prefix(from).unwrap_or(Path::new(""));
+                let target = if rest.as_os_str().is_empty() {
+                    to.to_path_buf()
+                } else {
+                    to.join(rest)
+                };
+                props.insert(target, p);
+            }
+        }
+    }
+
+    /// Handles `COPY` and `MOVE`, which differ only by removal of the source.
+    fn transfer<'b>(&self, req: &Request, is_move: bool) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        // A moved symlink is renamed itself, a copied one is read like by `GET`.
+        let source = if is_move {
+            self.locate_existing_entry(&path)?
+        } else {
+            self.locate_existing(&path)?
+        };
+        let dest_path = self.destination(req)?;
+        let dest = self.locate_entry(&dest_path)?;
+
+        if (is_move && source.path == "/")
+            || dest.path == "/"
+            || source.abspath == dest.abspath
+            || (source.is_dir() && dest.abspath.starts_with(&source.abspath))
+        {
+            return Err(StatusCode::Forbidden);
+        }
+
+        // Moved collections always include members, copies may skip them.
+        let recursive = match depth(req, Depth::Infinity)? {
+            Depth::Infinity => true,
+            Depth::Zero if !is_move => false,
+            _ => return Err(StatusCode::BadRequest),
+        };
+
+        let overwrite = match req.headers.get(HeaderNames::Overwrite.as_ref()) {
+            None | Some("T") => true,
+            Some("F") => false,
+            Some(_) => return Err(StatusCode::BadRequest),
+        };
+
+        if is_move {
+            self.check_locks(req, &source.path, true)?;
+        }
+        self.check_locks(req, &dest.path, true)?;
+
+        let existed = dest.stat.is_some();
+        if existed && !overwrite {
+            return Err(StatusCode::PreconditionFailed);
+        }
+
+        // Renaming fails across file systems, then the tree is copied.
+        let renamed = if is_move {
+            match replace(&source.abspath, &dest.abspath) {
+                Ok(()) => true,
+                Err(err) if err.kind() == io::ErrorKind::CrossesDevices => false,
+                Err(err) => return Err(io_status(&path, err)),
+            }
+        } else {
+            false
+        };
+        if !renamed {
+            // The copy is staged next to the destination, which is replaced only once
+            // it's complete.
+            let staged = upload::temp_path(&dest.abspath);
+            copy_tree(&source.abspath, &staged, recursive)
+                .and_then(|_| replace(&staged, &dest.abspath))
+                .map_err(|err| {
+                    let _ = remove_tree(&staged);
+                    io_status(&path, err)
+                })?;
+            if is_move {
+                remove_tree(&source.abspath).map_err(|err| io_status(&path, err))?;
+            }
+        }
+
+        if existed {
+            self.transfer_props(&dest.abspath, None);
+            self.locks.remove_tree(&dest.path);
+        }
+        self.transfer_props(&source.abspath, Some((&dest.abspath, !is_move)));
+        if is_move {
+            self.locks.remove_tree(&source.path);
+            self.handler.remove_sidecars(&source.path);
+        }
+        self.handler.remove_sidecars(&dest.path);
+
+        if existed {
+            Ok(Response::new(StatusCode::NoContent))
+        } else {
+            Ok(Response::new(StatusCode::Created).with_content_length(0))
+        }
+    }
+
+    /// Returns normalized path of `Destination` header.
+    ///
+    /// Destinations on other hosts or outside of the handler's URL prefix are rejected
+    /// with 502, as another server would have to handle them.
+    fn destination(&self, req: &Request) -> Result<String, StatusCode> {
+        let value = req
+            .headers
+            .get(HeaderNames::Destination.as_ref())
+            .ok_or(StatusCode::BadRequest)?;
+
+        let path = match value.split_once("://") {
+            Some((_, rest)) => {
+                let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+                // Without `Host` the authority can't be verified.
+                let host = req
+                    .headers
+                    .get(HeaderNames::Host.as_ref())
+                    .ok_or(StatusCode::BadRequest)?;
+                if !host.trim().eq_ignore_ascii_case(authority) {
+                    return Err(StatusCode::BadGateway);
+                }
+                path
+            }
+            None => value,
+        };
+
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let path = http::decode_path(path).map_err(|_| StatusCode::BadRequest)?;
+        let prefix = &self.handler.url_prefix;
+        let path = match path.strip_prefix(prefix.as_str()) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+            _ => return Err(StatusCode::BadGateway),
+        };
+        path::normalize(path).map_err(|err| err.status_code())
+    }
+
+    fn lock<'b>(&self, req: &mut Request) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        let timeout = req
+            .headers
+            .get(HeaderNames::Timeout.as_ref())
+            .and_then(lock::parse_timeout);
+        let body = read_body(req)?;
+
+        // Request without a body refreshes a lock identified by `If` header.
+        if body.trim().is_empty() {
+            let tokens = self.submitted_tokens(req)?;
+            let lock = self
+                .locks
+                .refresh(&path, &tokens, timeout)
+                .ok_or(StatusCode::PreconditionFailed)?;
+            return Ok(self.lock_response(StatusCode::OK, &lock));
+        }
+
+        let doc = roxmltree::Document::parse(&body).map_err(|_| StatusCode::BadRequest)?;
+        let root = doc.root_element();
+        let child = |name| root.children().find(|n| is_dav(*n, name));
+        let scope = child("lockscope").ok_or(StatusCode::BadRequest)?;
+        let exclusive = scope.children().any(|n| is_dav(n, "exclusive"));
+        let write = child("locktype").is_some_and(|t| t.children().any(|n| is_dav(n, "write")));
+        if !is_dav(root, "lockinfo") || !write {
+            return Err(StatusCode::BadRequest);
+        }
+        let owner = child("owner").map(inner_xml);
+
+        let recursive = match depth(req, Depth::Infinity)? {
+            Depth::Zero => false,
+            Depth::Infinity => true,
+            Depth::One => return Err(StatusCode::BadRequest),
+        };
+
+        let resource = self.locate(&path)?;
+        self.submitted_tokens(req)?;
+        let lock = self
+            .locks
+            .lock(&resource.path, recursive, exclusive, owner, timeout)
+            .map_err(|_| StatusCode::Locked)?;
+
+        // Locking an unmapped path creates an empty file.
+        if resource.stat.is_some() {
+            return Ok(self.lock_response(StatusCode::OK, &lock));
+        }
+        if let Err(err) = fs::File::create(&resource.abspath) {
+            self.locks.unlock(&resource.path, &lock.token);
+            return Err(io_status(&path, err));
+        }
+        Ok(self.lock_response(StatusCode::Created, &lock))
+    }
+
+    fn lock_response<'b>(&self, status: StatusCode, lock: &Lock) -> Response<'b> {
+        let body = format!(
+            "{XML_DECLARATION}<D:prop xmlns:D=\"DAV:\"><D:lockdiscovery>{}</D:lockdiscovery></D:prop>",
+            self.active_lock(lock)
+        );
+        Response::new(status)
+            .with_content_type(XML_CONTENT_TYPE.to_owned())
+            .with_content_length(body.len() as u64)
+            .with_header(
+                HeaderNames::LockToken.as_ref(),
+                &format!("<{}>", lock.token),
+            )
+            .with_body(body)
+    }
+
+    fn unlock<'b>(&self, req: &Request) -> Result<Response<'b>, StatusCode> {
+        let path = Self::request_path(req)?;
+        let token = req
+            .headers
+            .get(HeaderNames::LockToken.as_ref())
+            .map(|t| t.trim().trim_start_matches('<').trim_end_matches('>'))
+            .ok_or(StatusCode::BadRequest)?;
+
+        if self.locks.unlock(&path, token) {
+            Ok(Response::new(StatusCode::NoContent))
+        } else {
+            Ok(dav_error(
+                StatusCode::Conflict,
+                "<D:lock-token-matches-request-uri/>",
+            ))
+        }
+    }
+}
+
+impl Handler for WebDav {
+    fn handle_request<'a, 'b>(&self, mut req: Request<'a>) -> Response<'b> {
+        if is_write(req.method) {
+            if let Err(status) = self.authorize(&req) {
+                return self.handler.error_response(status);
+            }
+        }
+
+        let result = match req.method {
+            Method::OPTIONS => Ok(self.options()),
+            Method::PROPFIND => self.propfind(&mut req),
+            Method::PROPPATCH => self.proppatch(&mut req),
+            Method::MKCOL => self.mkcol(&mut req),
+            Method::PUT => self.put(&mut req),
+            Method::DELETE => self.delete(&req),
+            Method::COPY => self.transfer(&req, false),
+            Method::MOVE => self.transfer(&req, true),
+            Method::LOCK => self.lock(&mut req),
+            Method::UNLOCK => self.unlock(&req),
+            _ => return self.handler.handle_request(req),
+        };
+
+        result.unwrap_or_else(|status| self.handler.error_response(status))
+    }
+
+    fn handle_bad_request(&self, err: &ParseError) -> Response<'_> {
+        self.handler.handle_bad_request(err)
+    }
+}
+
+/// Returns whether a method modifies files, properties or locks.
+fn is_write(method: Method) -> bool {
+    matches!(
+        method,
+        Method::PUT
+            | Method::DELETE
+            | Method::PROPPATCH
+            | Method::MKCOL
+            | Method::COPY
+            | Method::MOVE
+            | Method::LOCK
+            | Method::UNLOCK
+    )
+}
+
+/// Reads XML request body, empty string is returned for requests without a body.
+fn read_body(req: &mut Request) -> Result<String, StatusCode> {
+    let mut body = Vec::new();
+    req.take(MAX_REQUEST_SIZE + 1)
+        .read_to_end(&mut body)
+        .map_err(|_| StatusCode::BadRequest)?;
+
+    if body.len() as u64 > MAX_REQUEST_SIZE {
+        return Err(StatusCode::PayloadTooLarge);
+    }
+    String::from_utf8(body).map_err(|_| StatusCode::BadRequest)
+}
+
+/// Parses `Depth` header, `default` is used if it's missing.
+fn depth(req: &Request, default: Depth) -> Result<Depth, StatusCode> {
+    match req.headers.get(HeaderNames::Depth.as_ref()).map(str::trim) {
+        None => Ok(default),
+        Some("0") => Ok(Depth::Zero),
+        Some("1") => Ok(Depth::One),
+        Some(d) if d.eq_ignore_ascii_case("infinity") => Ok(Depth::Infinity),
+        Some(_) => Err(StatusCode::BadRequest),
+    }
+}
+
+fn parse_propfind(body: &str) -> Result<PropFind, StatusCode> {
+    // Empty body requests all properties.
+    if body.trim().is_empty() {
+        return Ok(PropFind::AllProp);
+    }
+
+    let doc = roxmltree::Document::parse(body).map_err(|_| StatusCode::BadRequest)?;
+    let root = doc.root_element();
+    if !is_dav(root, "propfind") {
+        return Err(StatusCode::BadRequest);
+    }
+
+    let query = root.children().find_map(|n| {
+        if is_dav(n, "allprop") {
+            Some(PropFind::AllProp)
+        } else if is_dav(n, "propname") {
+            Some(PropFind::PropName)
+        } else if is_dav(n, "prop") {
+            let names = n.children().filter(|p| p.is_element());
+            Some(PropFind::Prop(names.map(PropName::of).collect()))
+        } else {
+            None
+        }
+    });
+    query.ok_or(StatusCode::BadRequest)
+}
+
+/// Returns whether a node is an element of the `DAV:` namespace.
+fn is_dav(node: roxmltree::Node, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(DAV_NS)
+        && node.tag_name().name() == name
+}
+
+/// Serializes contents of an element into a standalone XML fragment.
+///
+/// Every element declares its own namespace, so the fragment can be embedded anywhere.
+fn inner_xml(node: roxmltree::Node) -> String {
+    let mut out = String::new();
+    for child in node.children() {
+        if child.is_text() {
+            out.push_str(&escape::html(child.text().unwrap_or_default()));
+            continue;
+        }
+        if !child.is_element() {
+            continue;
+        }
+
+        let tag = child.tag_name();
+        let name = match tag.namespace() {
+            Some(ns) => {
+                let _ = write!(out, "<X:{} xmlns:X=\"{}\"", tag.name(), escape::html(ns));
+                format!("X:{}", tag.name())
+            }
+            None => {
+                let _ = write!(out, "<{}", tag.name());
+                tag.name().to_owned()
+            }
+        };
+        for attr in child.attributes().filter(|a| a.namespace().is_none()) {
+            let _ = write!(out, " {}=\"{}\"", attr.name(), escape::html(attr.value()));
+        }
+
+        if child.has_children() {
+            let _ = write!(out, ">{}</{name}>", inner_xml(child));
+        } else {
+            out.push_str("/>");
+        }
+    }
+    out
+}
+
+fn write_propstat(out: &mut String, props: &str, status: StatusCode) {
+    let _ = write!(
+        out,
+        "<D:propstat><D:prop>{props}</D:prop><D:status>HTTP/1.1 {status} {}</D:status></D:propstat>",
+        status.phrase()
+    );
+}
+
+fn multistatus<'b>(body: String) -> Response<'b> {
+    Response::new(StatusCode::MultiStatus)
+        .with_content_type(XML_CONTENT_TYPE.to_owned())
+        .with_content_length(body.len() as u64)
+        .with_body(body)
+}
+
+/// Creates response with a `DAV:error` body naming the failed condition.
+fn dav_error<'b>(status: StatusCode, condition: &str) -> Response<'b> {
+    let body = format!("{XML_DECLARATION}<D:error xmlns:D=\"DAV:\">{condition}</D:error>");
+    Response::new(status)
+        .with_content_type(XML_CONTENT_TYPE.to_owned())
+        .with_content_length(body.len() as u64)
+        .with_body(body)
+}
+
+/// Maps file system error to a response status, details are only logged.
+fn io_status(path: &str, err: io::Error) -> StatusCode {
+    println!("WebDAV request to {path} failed: {err}");
+    match err.kind() {
+        io::ErrorKind::NotFound => StatusCode::Conflict,
+        io::ErrorKind::PermissionDenied => StatusCode::Forbidden,
+        io::ErrorKind::AlreadyExists => StatusCode::MethodNotAllowed,
+        io::ErrorKind::StorageFull => StatusCode::InsufficientStorage,
+        _ => StatusCode::InternalServerError,
+    }
+}
+
+/// Renames a file or a collection over a target, which may be a non-empty collection.
+///
+/// The target is moved aside first and restored if the rename fails.
+fn replace(from: &Path, to: &Path) -> io::Result<()> {
+    let backup = upload::temp_path(to);
+    let backed_up = match fs::rename(to, &backup) {
+        Ok(()) => true,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+        Err(err) => return Err(err),
+    };
+
+    if let Err(err) = fs::rename(from, to) {
+        if backed_up {
+            let _ = fs::rename(&backup, to);
+        }
+        return Err(err);
+    }
+
+    if backed_up {
+        if let Err(err) = remove_tree(&backup) {
+            println!("failed to remove {}: {err}", backup.display());
+        }
+    }
+    Ok(())
+}
+
+/// Removes a file or a collection with its members, symlinks are removed themselves.
+fn remove_tree(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Copies a file or a collection, symlinks inside collections are skipped.
+fn copy_tree(from: &Path, to: &Path, recursive: bool) -> io::Result<()> {
+    if !fs::metadata(from)?.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+
+    fs::create_dir(to)?;
+    if recursive {
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_symlink() {
+                copy_tree(&entry.path(), &to.join(entry.file_name()), true)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Formats time as RFC 3339 timestamp in UTC, used by `DAV:creationdate`.
+fn format_rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Civil date from days since epoch (H. Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::temp_dir::TempDir;
+    use crate::handler::{SymlinkPolicy, Uploads};
+
+    const AUTH: &str = "Authorization: Bearer secret\r\n";
+
+    fn dav(dir: &TempDir) -> WebDav {
+        let uploads = Uploads::default().with_bearer_token("secret");
+        WebDav::new(StaticHandler::new(dir.0.display().to_string()).with_uploads(uploads))
+    }
+
+    fn send(dav: &WebDav, head: &str) -> Response<'static> {
+        let raw = format!("{head}\r\n");
+        let mut src = raw.as_bytes();
+        let req = Request::from_reader(&mut src).unwrap();
+        dav.handle_request(req)
+    }
+
+    fn status(rsp: &Response) -> u16 {
+        rsp.status_code as u16
+    }
+
+    #[test]
+    fn writes_require_authorisation() {
+        let dir = TempDir::new();
+        let dav = dav(&dir);
+
+        let rsp = send(&dav, "MKCOL /new HTTP/1.1\r\nHost: h\r\n");
+        assert_eq!(status(&rsp), 401);
+        assert_eq!(
+            rsp.headers.get(HeaderNames::WWWAuthenticate.as_ref()),
+            Some("Bearer")
+        );
+
+        let rsp = send(
+            &dav,
+            "MKCOL /new HTTP/1.1\r\nHost: h\r\nAuthorization: Bearer wrong\r\n",
+        );
+        assert_eq!(status(&rsp), 403);
+
+        let rsp = send(&dav, &format!("MKCOL /new HTTP/1.1\r\nHost: h\r\n{AUTH}"));
+        assert_eq!(status(&rsp), 201);
+        assert!(dir.0.join("new").is_dir());
+
+        let rsp = send(&dav, "PROPFIND /new HTTP/1.1\r\nHost: h\r\nDepth: 0\r\n");
+        assert_eq!(status(&rsp), 207);
+    }
+
+    #[test]
+    fn read_only_without_uploads() {
+        let dir = TempDir::new();
+        let dav = WebDav::new(StaticHandler::new(dir.0.display().to_string()));
+        for method in ["MKCOL", "DELETE", "LOCK", "UNLOCK", "PROPPATCH"] {
+            let rsp = send(&dav, &format!("{method} /x HTTP/1.1\r\nHost: h\r\n{AUTH}"));
+            assert_eq!(status(&rsp), 403, "{method}");
+        }
+        let rsp = send(&dav, "OPTIONS / HTTP/1.1\r\nHost: h\r\n");
+        assert_eq!(status(&rsp), 200);
+    }
+
+    #[test]
+    fn destination_authority_is_verified() {
+        let dir = TempDir::new();
+        fs::write(dir.0.join("a.txt"), "a").unwrap();
+        let dav = dav(&dir);
+        let copy = |headers: &str| {
+            let head =
+                format!("COPY /a.txt HTTP/1.1\r\n{AUTH}Destination: http://h/b.txt\r\n{headers}");
+            status(&send(&dav, &head))
+        };
+
+        assert_eq!(copy(""), 400);
+        assert_eq!(copy("Host: other\r\n"), 502);
+        assert_eq!(copy("Host: h\r\n"), 201);
+        assert_eq!(fs::read_to_string(dir.0.join("b.txt")).unwrap(), "a");
+    }
+
+    #[test]
+    fn copy_replaces_collection() {
+        let dir = TempDir::new();
+        fs::create_dir_all(dir.0.join("src/in")).unwrap();
+        fs::write(dir.0.join("src/in/new"), "new").unwrap();
+        fs::create_dir_all(dir.0.join("dst")).unwrap();
+        fs::write(dir.0.join("dst/old"), "old").unwrap();
+        let dav = dav(&dir);
+
+        let head =
+            format!("COPY /src HTTP/1.1\r\nHost: h\r\n{AUTH}Destination: /dst\r\nOverwrite: F\r\n");
+        assert_eq!(status(&send(&dav, &head)), 412);
+        assert!(dir.0.join("dst/old").exists());
+
+        let head = format!("COPY /src HTTP/1.1\r\nHost: h\r\n{AUTH}Destination: /dst\r\n");
+        assert_eq!(status(&send(&dav, &head)), 204);
+        assert!(!dir.0.join("dst/old").exists());
+        assert_eq!(fs::read_to_string(dir.0.join("dst/in/new")).unwrap(), "new");
+
+        assert_eq!(dir.names(), ["dst", "src"]);
+    }
+
+    #[test]
+    fn move_replaces_file() {
+        let dir = TempDir::new();
+        fs::write(dir.0.join("a"), "a").unwrap();
+        fs::create_dir(dir.0.join("b")).unwrap();
+        let dav = dav(&dir);
+
+        let head = format!("MOVE /a HTTP/1.1\r\nHost: h\r\n{AUTH}Destination: /b\r\n");
+        assert_eq!(status(&send(&dav, &head)), 204);
+        assert!(!dir.0.join("a").exists());
+        assert_eq!(fs::read_to_string(dir.0.join("b")).unwrap(), "a");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn delete_and_move_symlinks_themselves() {
+        use std::os::unix::fs::symlink;
+        let outside = TempDir::new();
+        fs::write(outside.0.join("file"), "outside").unwrap();
+        let dir = TempDir::new();
+        fs::write(dir.0.join("target"), "target").unwrap();
+        symlink(dir.0.join("target"), dir.0.join("link")).unwrap();
+        symlink(&outside.0, dir.0.join("out")).unwrap();
+        let uploads = Uploads::default().with_bearer_token("secret");
+        let handler = StaticHandler::new(dir.0.display().to_string())
+            .with_symlinks(SymlinkPolicy::AllowAll)
+            .with_uploads(uploads);
+        let dav = WebDav::new(handler);
+
+        let rsp = send(&dav, &format!("DELETE /link HTTP/1.1\r\nHost: h\r\n{AUTH}"));
+        assert_eq!(status(&rsp), 204);
+        assert_eq!(dir.names(), ["out", "target"]);
+
+        let head = format!("MOVE /out HTTP/1.1\r\nHost: h\r\n{AUTH}Destination: /moved\r\n");
+        assert_eq!(status(&send(&dav, &head)), 201);
+        assert!(fs::symlink_metadata(dir.0.join("moved"))
+            .unwrap()
+            .is_symlink());
+
+        let rsp = send(
+            &dav,
+            &format!("DELETE /moved HTTP/1.1\r\nHost: h\r\n{AUTH}"),
+        );
+        assert_eq!(status(&rsp), 204);
+        assert_eq!(dir.names(), ["target"]);
+        assert_eq!(
+            fs::read_to_string(outside.0.join("file")).unwrap(),
+            "outside"
+        );
+
+        let head = format!("MOVE /target HTTP/1.1\r\nHost: h\r\n{AUTH}Destination: /\r\n");
+        assert_eq!(status(&send(&dav, &head)), 403);
+    }
+
+    #[test]
+    fn stale_sidecars_are_removed() {
+        let dir = TempDir::new();
+        for name in ["a.js", "a.js.gz", "b.js", "b.js.br"] {
+            fs::write(dir.0.join(name), name).unwrap();
+        }
+        let dav = dav(&dir);
+
+        let head = format!("COPY /a.js HTTP/1.1\r\nHost: h\r\n{AUTH}Destination: /b.js\r\n");
+        assert_eq!(status(&send(&dav, &head)), 204);
+        assert_eq!(dir.names(), ["a.js", "a.js.gz", "b.js"]);
+
+        let head = format!("MOVE /a.js HTTP/1.1\r\nHost: h\r\n{AUTH}Destination: /c.js\r\n");
+        assert_eq!(status(&send(&dav, &head)), 201);
+        assert_eq!(dir.names(), ["b.js", "c.js"]);
+
+        fs::write(dir.0.join("c.js.zst"), "stale").unwrap();
+        let rsp = send(&dav, &format!("DELETE /c.js HTTP/1.1\r\nHost: h\r\n{AUTH}"));
+        assert_eq!(status(&rsp), 204);
+        assert_eq!(dir.names(), ["b.js"]);
+    }
+
+    #[test]
+    fn failed_replace_restores_target() {
+        let dir = TempDir::new();
+        let target = dir.0.join("target");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("file"), "kept").unwrap();
+
+        assert!(replace(&dir.0.join("missing"), &target).is_err());
+        assert_eq!(fs::read_to_string(target.join("file")).unwrap(), "kept");
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn propfind_rejects_infinite_depth() {
+        let dir = TempDir::new();
+        let rsp = send(&dav(&dir), "PROPFIND / HTTP/1.1\r\nHost: h\r\n");
+        assert_eq!(status(&rsp), 403);
+    }
+
+    #[test]
+    fn rfc3339_dates() {
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + std::time::Duration::from_secs(951_827_696);
+        assert_eq!(format_rfc3339(leap_day), "2000-02-29T12:34:56Z");
+    }
+}
@@ -0,0 +1,57 @@
+/// Picks the offered media type most preferred by a client using `Accept` header value.
+///
+/// Supports `type/*` and `*/*` wildcards and quality values.
+/// Ties are resolved by the order of offers.
+pub fn preferred<'o>(accept: &str, offers: &[&'o str]) -> Option<&'o str> {
+    let ranges: Vec<(String, f32)> = accept.split(',').filter_map(parse_range).collect();
+    offers
+        .iter()
+        .map(|offer| (*offer, quality(&ranges, offer)))
+        .filter(|(_, q)| *q > 0.0)
+        .fold(None, |best: Option<(&str, f32)>, (offer, q)| match best {
+            Some((_, best_q)) if best_q >= q => best,
+            _ => Some((offer, q)),
+        })
+        .map(|(offer, _)| offer)
+}
+
+/// Returns whether a client accepts the media type.
+pub fn accepts(accept: &str, media_type: &str) -> bool {
+    preferred(accept, &[media_type]).is_some()
+}
+
+/// Returns whether a client lists the media type itself, not only through wildcards.
+///
+/// Browsers list `text/html` only for navigations, while subresource requests use `*/*`.
+pub fn accepts_explicitly(accept: &str, media_type: &str) -> bool {
+    accept
+        .split(',')
+        .filter_map(parse_range)
+        .any(|(range, q)| range.eq_ignore_ascii_case(media_type) && q > 0.0)
+}
+
+/// Returns quality of the most specific media range matching the offer.
+fn quality(ranges: &[(String, f32)], offer: &str) -> f32 {
+    let (offer_type, _) = offer.split_once('/').unwrap_or((offer, ""));
+    ranges
+        .iter()
+        .filter_map(|(range, q)| match range.split_once('/') {
+            _ if range == offer => Some((2, *q)),
+            Some((t, "*")) if t == offer_type => Some((1, *q)),
+            Some(("*", "*")) => Some((0, *q)),
+            _ => None,
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, q)| q)
+}
+
+/// Parses single `Accept` list item into lowercase media range and quality value.
+fn parse_range(item: &str) -> Option<(String, f32)> {
+    let mut parts = item.split(';').map(str::trim);
+    let range = parts.next().filter(|s| !s.is_empty())?.to_lowercase();
+    let quality = parts
+        .find_map(|p| p.strip_prefix("q="))
+        .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+
+    Some((range, quality))
+}
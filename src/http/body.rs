@@ -0,0 +1,226 @@
+use super::sendfile::SendFile;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+
+/// Piece of a streamed body.
+pub enum Chunk {
+    /// Data to be written to a client.
+    Data(Vec<u8>),
+
+    /// Forces all previously written data to be sent to a client.
+    Flush,
+}
+
+/// Response body.
+#[derive(Default)]
+pub enum Body<'a> {
+    #[default]
+    Empty,
+
+    /// In-memory data.
+    Bytes(Vec<u8>),
+
+    /// In-memory data shared between responses, e.g. a cached file.
+    Shared(Arc<[u8]>),
+
+    /// Pull-style reader with unknown length.
+    Reader(Box<dyn Read + 'a>),
+
+    /// Part of a file with known length.
+    File { file: File, offset: u64, len: u64 },
+
+    /// Push-style producer. Body ends when iterator is exhausted.
+    Stream(Box<dyn Iterator<Item = io::Result<Chunk>> + 'a>),
+}
+
+impl<'a> Body<'a> {
+    /// Creates a file body using file size as body length.
+    pub fn file(file: File) -> io::Result<Self> {
+        let len = file.metadata()?.len();
+        Ok(Self::File {
+            file,
+            offset: 0,
+            len,
+        })
+    }
+
+    /// Creates a body from `len` bytes of a file starting at `offset`.
+    pub fn file_range(file: File, offset: u64, len: u64) -> Self {
+        Self::File { file, offset, len }
+    }
+
+    /// Creates a body streamed from an iterator.
+    pub fn stream(iter: impl Iterator<Item = io::Result<Chunk>> + 'a) -> Self {
+        Self::Stream(Box::new(iter))
+    }
+
+    /// Creates a body which receives data from a [BodySender].
+    ///
+    /// Sender can be moved to another thread. Body ends when sender is dropped.
+    pub fn channel() -> (BodySender, Self) {
+        let (tx, rx) = mpsc::channel();
+        (BodySender { tx }, Self::Stream(Box::new(rx.into_iter())))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Empty => true,
+            Self::Bytes(b) => b.is_empty(),
+            Self::Shared(b) => b.is_empty(),
+            Self::File { len, .. } => *len == 0,
+            _ => false,
+        }
+    }
+
+    /// Returns body length if it's known in advance.
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Self::Empty => Some(0),
+            Self::Bytes(b) => Some(b.len() as u64),
+            Self::Shared(b) => Some(b.len() as u64),
+            Self::File { len, .. } => Some(*len),
+            Self::Reader(_) | Self::Stream(_) => None,
+        }
+    }
+
+    /// Converts body into a reader. Flush points of streamed bodies are discarded.
+    pub fn into_reader(self) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Self::Empty => Box::new(io::empty()),
+            Self::Bytes(b) => Box::new(Cursor::new(b)),
+            Self::Shared(b) => Box::new(Cursor::new(b)),
+            Self::Reader(r) => r,
+            Self::File {
+                mut file,
+                offset,
+                len,
+            } => {
+                file.seek(SeekFrom::Start(offset))?;
+                Box::new(file.take(len))
+            }
+            Self::Stream(iter) => Box::new(StreamReader {
+                iter,
+                buf: Cursor::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Writes body contents to a writer and flushes it at stream flush points.
+    pub fn write_to(self, w: &mut impl Write) -> io::Result<u64> {
+        match self {
+            Self::Empty => Ok(0),
+            Self::Bytes(b) => w.write_all(&b).map(|_| b.len() as u64),
+            Self::Shared(b) => w.write_all(&b).map(|_| b.len() as u64),
+            Self::Reader(mut r) => io::copy(&mut r, w),
+            Self::File { file, offset, len } => {
+                let mut file = &file;
+                file.seek(SeekFrom::Start(offset))?;
+                io::copy(&mut file.take(len), w)
+            }
+            Self::Stream(iter) => {
+                let mut written = 0;
+                for chunk in iter {
+                    match chunk? {
+                        Chunk::Data(data) => {
+                            w.write_all(&data)?;
+                            written += data.len() as u64;
+                        }
+                        Chunk::Flush => w.flush()?,
+                    }
+                }
+                Ok(written)
+            }
+        }
+    }
+
+    /// Writes body contents like [Body::write_to], but transfers file bodies using [SendFile].
+    pub fn send_to(self, w: &mut impl SendFile) -> io::Result<u64> {
+        match self {
+            Self::File { file, offset, len } => w.send_file(&file, offset, len),
+            body => body.write_to(w),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body<'_> {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Bytes(value)
+    }
+}
+
+impl From<Arc<[u8]>> for Body<'_> {
+    fn from(value: Arc<[u8]>) -> Self {
+        Self::Shared(value)
+    }
+}
+
+impl From<String> for Body<'_> {
+    fn from(value: String) -> Self {
+        Self::Bytes(value.into_bytes())
+    }
+}
+
+impl<'a> From<Box<dyn Read + 'a>> for Body<'a> {
+    fn from(value: Box<dyn Read + 'a>) -> Self {
+        Self::Reader(value)
+    }
+}
+
+/// Sending half of a [Body::channel].
+///
+/// Each write is sent as a separate chunk, `flush` sends a [Chunk::Flush].
+pub struct BodySender {
+    tx: Sender<io::Result<Chunk>>,
+}
+
+impl BodySender {
+    /// Aborts body transfer with an error. Client connection is closed without completing the body.
+    pub fn abort(self, err: io::Error) {
+        let _ = self.tx.send(Err(err));
+    }
+
+    fn send(&self, chunk: Chunk) -> io::Result<()> {
+        self.tx
+            .send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "response body was dropped"))
+    }
+}
+
+impl Write for BodySender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.send(Chunk::Data(buf.to_vec()))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send(Chunk::Flush)
+    }
+}
+
+/// Adapts a chunk iterator to [Read].
+struct StreamReader<'a> {
+    iter: Box<dyn Iterator<Item = io::Result<Chunk>> + 'a>,
+    buf: Cursor<Vec<u8>>,
+}
+
+impl Read for StreamReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.buf.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+
+            match self.iter.next() {
+                Some(Ok(Chunk::Data(data))) => self.buf = Cursor::new(data),
+                Some(Ok(Chunk::Flush)) => {}
+                Some(Err(err)) => return Err(err),
+                None => return Ok(0),
+            }
+        }
+    }
+}
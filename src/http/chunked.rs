@@ -0,0 +1,206 @@
+use super::header::Headers;
+use super::request::READ_LIMIT;
+use std::io::{self, BufRead, Read, Write};
+
+const CHUNK_DELIMITER: &[u8; 2] = b"\r\n";
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+
+/// Writer which encodes data using `Transfer-Encoding: chunked`.
+///
+/// Each non-empty write is emitted as a separate chunk prefixed with its size.
+/// Call [ChunkedWriter::finish] to write the terminating zero-length chunk and optional trailers.
+pub struct ChunkedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Writes the last chunk followed by trailer fields and returns the underlying writer.
+    pub fn finish(mut self, trailers: Option<&Headers>) -> io::Result<W> {
+        self.inner.write_all(b"0\r\n")?;
+        if let Some(trailers) = trailers.filter(|t| !t.is_empty()) {
+            trailers.send(&mut self.inner)?;
+            self.inner.write_all(CHUNK_DELIMITER)?;
+        }
+
+        self.inner.write_all(CHUNK_DELIMITER)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Zero-length chunk means end of the body, so skip empty writes.
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        write!(self.inner, "{:X}\r\n", buf.len())?;
+        self.inner.write_all(buf)?;
+        self.inner.write_all(CHUNK_DELIMITER)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader which decodes body sent using `Transfer-Encoding: chunked`.
+///
+/// Trailer fields sent by a client are available after the body is fully read.
+pub struct ChunkedReader<R: BufRead> {
+    inner: R,
+    remaining: u64,
+    trailers: Option<Headers>,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            trailers: None,
+        }
+    }
+
+    /// Returns trailer fields if the last chunk was already read.
+    pub fn trailers(&self) -> Option<&Headers> {
+        self.trailers.as_ref()
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        (&mut self.inner)
+            .take(MAX_LINE_LENGTH)
+            .read_line(&mut line)?;
+
+        match line.strip_suffix("\r\n") {
+            Some(v) => Ok(v.to_owned()),
+            None => Err(invalid_data("malformed chunk line")),
+        }
+    }
+
+    /// Reads chunk size line. Chunk extensions are ignored.
+    fn read_chunk_size(&mut self) -> io::Result<u64> {
+        let line = self.read_line()?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        if !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid_data("invalid chunk size"));
+        }
+        u64::from_str_radix(size, 16).map_err(|_| invalid_data("invalid chunk size"))
+    }
+
+    fn read_trailers(&mut self) -> io::Result<Headers> {
+        let mut fields = Vec::new();
+        let mut size = 0;
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                return Ok(Headers::from(fields.join("\r\n").as_str()));
+            }
+
+            size += line.len() + CHUNK_DELIMITER.len();
+            if size > READ_LIMIT {
+                return Err(invalid_data("trailer section is too large"));
+            }
+            fields.push(line);
+        }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.trailers.is_some() || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            self.remaining = self.read_chunk_size()?;
+            if self.remaining == 0 {
+                self.trailers = Some(self.read_trailers()?);
+                return Ok(0);
+            }
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.remaining -= n as u64;
+        if self.remaining == 0 && !self.read_line()?.is_empty() {
+            return Err(invalid_data("missing chunk delimiter"));
+        }
+
+        Ok(n)
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(body: &str) -> io::Result<(String, ChunkedReader<&[u8]>)> {
+        let mut reader = ChunkedReader::new(body.as_bytes());
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        Ok((data, reader))
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        let mut writer = ChunkedWriter::new(Vec::new());
+        writer.write_all(b"hello ").unwrap();
+        writer.write_all(b"").unwrap();
+        writer.write_all(b"world").unwrap();
+        let mut trailers = Headers::new();
+        trailers.insert("Digest", "x");
+        let encoded = writer.finish(Some(&trailers)).unwrap();
+
+        let encoded = String::from_utf8(encoded).unwrap();
+        assert!(encoded.starts_with("6\r\nhello \r\n5\r\nworld\r\n0\r\n"));
+        let (data, reader) = decode(&encoded).unwrap();
+        assert_eq!(data, "hello world");
+        assert_eq!(reader.trailers().unwrap().get("digest"), Some("x"));
+    }
+
+    #[test]
+    fn reader_ignores_extensions() {
+        let (data, reader) = decode("a;name=value\r\n0123456789\r\n0\r\n\r\n").unwrap();
+        assert_eq!(data, "0123456789");
+        assert!(reader.trailers().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reader_rejects_invalid_sizes() {
+        for size in ["+1", "-1", "", "x", "1 1", "10000000000000000"] {
+            let body = format!("{size}\r\na\r\n0\r\n\r\n");
+            let err = decode(&body).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{size:?}");
+        }
+    }
+
+    #[test]
+    fn reader_rejects_malformed_chunks() {
+        let err = decode("2\r\nabc\r\n0\r\n\r\n").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = decode("5\r\nab").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = decode("1\na\r\n").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let trailers = "x-a: b\r\n".repeat(READ_LIMIT / 8 + 1);
+        let err = decode(&format!("0\r\n{trailers}\r\n")).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,411 @@
+use super::{
+    body::{Body, Chunk},
+    header::Names,
+    response::Response,
+    status::StatusCode,
+};
+use std::io::{self, Write};
+use std::mem;
+use strum_macros::{AsRefStr, Display, EnumString};
+
+const BUFFER_SIZE: usize = 8 * 1024;
+const GZIP_LEVEL: u32 = 6;
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const ZSTD_LEVEL: i32 = 3;
+
+/// Media type of server-sent events, see [Compression::is_compressible_type].
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// Content codings supported by the server.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display, AsRefStr, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Encoding {
+    #[strum(serialize = "zstd")]
+    Zstd,
+
+    #[strum(serialize = "br")]
+    Brotli,
+
+    #[strum(to_string = "gzip", serialize = "x-gzip")]
+    Gzip,
+
+    #[strum(serialize = "deflate")]
+    Deflate,
+}
+
+impl Encoding {
+    /// Extension of precompressed sidecar files, e.g. `app.js.br`.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Zstd => Some("zst"),
+            Self::Brotli => Some("br"),
+            Self::Gzip => Some("gz"),
+            Self::Deflate => None,
+        }
+    }
+
+    /// Picks best of `offers` accepted by a client using `Accept-Encoding` header value.
+    ///
+    /// Client's quality values take precedence, ties are resolved by order of offers.
+    pub fn negotiate(offers: &[Encoding], accept_encoding: &str) -> Option<Encoding> {
+        let accepted: Vec<(&str, f32)> = accept_encoding
+            .split(',')
+            .filter_map(parse_coding)
+            .collect();
+
+        let quality = |enc: &Encoding| {
+            accepted
+                .iter()
+                .find(|(name, _)| name.parse::<Encoding>().ok().as_ref() == Some(enc))
+                .or_else(|| accepted.iter().find(|(name, _)| *name == "*"))
+                .map(|(_, q)| *q)
+                .unwrap_or(0.0)
+        };
+
+        offers
+            .iter()
+            .map(|enc| (*enc, quality(enc)))
+            .filter(|(_, q)| *q > 0.0)
+            .fold(None, |best: Option<(Encoding, f32)>, (enc, q)| match best {
+                Some((_, best_q)) if best_q >= q => best,
+                _ => Some((enc, q)),
+            })
+            .map(|(enc, _)| enc)
+    }
+
+    /// Compresses body on the fly.
+    ///
+    /// Streamed bodies keep their flush points, other bodies are compressed as readers.
+    pub fn encode<'a>(&self, body: Body<'a>) -> io::Result<Body<'a>> {
+        if let Body::Stream(iter) = body {
+            return Ok(Body::Stream(Box::new(EncodedStream {
+                iter,
+                encoder: Some(StreamEncoder::new(*self)?),
+                pending_flush: false,
+            })));
+        }
+
+        let src = body.into_reader()?;
+        let level = flate2::Compression::new(GZIP_LEVEL);
+        Ok(Body::Reader(match self {
+            Self::Zstd => Box::new(zstd::stream::read::Encoder::new(src, ZSTD_LEVEL)?),
+            Self::Brotli => Box::new(brotli::CompressorReader::new(
+                src,
+                BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW,
+            )),
+            Self::Gzip => Box::new(flate2::read::GzEncoder::new(src, level)),
+            Self::Deflate => Box::new(flate2::read::ZlibEncoder::new(src, level)),
+        }))
+    }
+}
+
+/// Push-style encoder used to compress streamed bodies.
+enum StreamEncoder {
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibEncoder<Vec<u8>>),
+}
+
+impl StreamEncoder {
+    fn new(encoding: Encoding) -> io::Result<Self> {
+        let level = flate2::Compression::new(GZIP_LEVEL);
+        Ok(match encoding {
+            Encoding::Zstd => {
+                Self::Zstd(zstd::stream::write::Encoder::new(Vec::new(), ZSTD_LEVEL)?)
+            }
+            Encoding::Brotli => Self::Brotli(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW,
+            ))),
+            Encoding::Gzip => Self::Gzip(flate2::write::GzEncoder::new(Vec::new(), level)),
+            Encoding::Deflate => Self::Deflate(flate2::write::ZlibEncoder::new(Vec::new(), level)),
+        })
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Zstd(w) => w,
+            Self::Brotli(w) => w,
+            Self::Gzip(w) => w,
+            Self::Deflate(w) => w,
+        }
+    }
+
+    /// Returns data compressed so far.
+    fn take_output(&mut self) -> Vec<u8> {
+        mem::take(match self {
+            Self::Zstd(w) => w.get_mut(),
+            Self::Brotli(w) => w.get_mut(),
+            Self::Gzip(w) => w.get_mut(),
+            Self::Deflate(w) => w.get_mut(),
+        })
+    }
+
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Zstd(w) => w.finish(),
+            Self::Brotli(w) => Ok((*w).into_inner()),
+            Self::Gzip(w) => w.finish(),
+            Self::Deflate(w) => w.finish(),
+        }
+    }
+}
+
+/// Compresses chunks of a streamed body, preserving flush points.
+struct EncodedStream<'a> {
+    iter: Box<dyn Iterator<Item = io::Result<Chunk>> + 'a>,
+    encoder: Option<StreamEncoder>,
+    pending_flush: bool,
+}
+
+impl Iterator for EncodedStream<'_> {
+    type Item = io::Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if mem::take(&mut self.pending_flush) {
+            return Some(Ok(Chunk::Flush));
+        }
+
+        loop {
+            let encoder = self.encoder.as_mut()?;
+            let result = match self.iter.next() {
+                Some(Ok(Chunk::Data(data))) => encoder.writer().write_all(&data),
+                Some(Ok(Chunk::Flush)) => {
+                    if let Err(err) = encoder.writer().flush() {
+                        return Some(Err(err));
+                    }
+
+                    // Emit compressed data first, then propagate flush.
+                    let out = encoder.take_output();
+                    if out.is_empty() {
+                        return Some(Ok(Chunk::Flush));
+                    }
+                    self.pending_flush = true;
+                    return Some(Ok(Chunk::Data(out)));
+                }
+                Some(Err(err)) => Err(err),
+                None => {
+                    let encoder = self.encoder.take()?;
+                    return Some(encoder.finish().map(Chunk::Data));
+                }
+            };
+
+            if let Err(err) = result {
+                self.encoder = None;
+                return Some(Err(err));
+            }
+
+            let out = encoder.take_output();
+            if !out.is_empty() {
+                return Some(Ok(Chunk::Data(out)));
+            }
+        }
+    }
+}
+
+/// Settings of on-the-fly response compression.
+pub struct Compression {
+    /// Encodings in order of server preference.
+    pub encodings: Vec<Encoding>,
+
+    /// Responses with known length smaller than this value are sent as is.
+    pub min_size: u64,
+
+    /// Compressible media types. Entries ending with `/*` match any subtype.
+    pub content_types: Vec<String>,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            encodings: vec![
+                Encoding::Zstd,
+                Encoding::Brotli,
+                Encoding::Gzip,
+                Encoding::Deflate,
+            ],
+            min_size: 1024,
+            content_types: [
+                "text/*",
+                "application/javascript",
+                "application/json",
+                "application/xml",
+                "application/wasm",
+                "application/problem+json",
+                "image/svg+xml",
+                "image/x-icon",
+                "font/ttf",
+                "font/otf",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl Compression {
+    /// Picks best encoding accepted by a client using `Accept-Encoding` header value.
+    ///
+    /// Client's quality values take precedence, ties are resolved by server preference.
+    pub fn negotiate(&self, accept_encoding: &str) -> Option<Encoding> {
+        Encoding::negotiate(&self.encodings, accept_encoding)
+    }
+
+    fn is_compressible(&self, rsp: &Response) -> bool {
+        if rsp.body.is_empty()
+            || rsp.headers.has(Names::ContentEncoding.as_ref())
+            || matches!(
+                rsp.status_code,
+                StatusCode::NoContent | StatusCode::NotModified | StatusCode::PartialContent
+            )
+        {
+            return false;
+        }
+
+        if matches!(rsp.headers.content_length(), Some(len) if len < self.min_size) {
+            return false;
+        }
+
+        rsp.headers
+            .get(Names::ContentType.as_ref())
+            .is_some_and(|v| self.is_compressible_type(v))
+    }
+
+    /// Checks whether media type of `Content-Type` value is in the compressible list.
+    ///
+    /// Event streams are never compressed, as encoders would hold events back.
+    pub fn is_compressible_type(&self, content_type: &str) -> bool {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if essence == EVENT_STREAM {
+            return false;
+        }
+
+        self.content_types
+            .iter()
+            .any(|t| match t.strip_suffix('*') {
+                Some(prefix) => essence.starts_with(prefix),
+                None => essence == *t,
+            })
+    }
+
+    /// Compresses response body if both response and client allow it.
+    ///
+    /// Compressed body has unknown length, so response is sent using chunked encoding.
+    /// Strong `ETag` is weakened, as it identifies the uncompressed representation.
+    pub fn apply<'a>(&self, mut rsp: Response<'a>, accept_encoding: Option<&str>) -> Response<'a> {
+        if !self.is_compressible(&rsp) {
+            return rsp;
+        }
+
+        rsp.headers
+            .append(Names::Vary.as_ref(), Names::AcceptEncoding.as_ref());
+        let Some(encoding) = accept_encoding.and_then(|v| self.negotiate(v)) else {
+            return rsp;
+        };
+
+        match encoding.encode(mem::take(&mut rsp.body)) {
+            Ok(body) => {
+                rsp.headers.remove(Names::ContentLength.as_ref());
+                // Encoded bytes differ from the identity ones, so a strong tag no longer applies.
+                if let Some(etag) = rsp.headers.get(Names::ETag.as_ref()) {
+                    if !etag.starts_with("W/") {
+                        let weak = format!("W/{etag}");
+                        rsp.headers.insert(Names::ETag.as_ref(), &weak);
+                    }
+                }
+                rsp.headers
+                    .insert(Names::ContentEncoding.as_ref(), encoding.as_ref());
+                rsp.with_body(body)
+            }
+            Err(err) => {
+                println!("failed to create {encoding} encoder - {err}");
+                Response::string(
+                    StatusCode::InternalServerError,
+                    "Failed to compress response".to_owned(),
+                )
+            }
+        }
+    }
+}
+
+/// Parses single `Accept-Encoding` list item into coding name and quality value.
+fn parse_coding(item: &str) -> Option<(&str, f32)> {
+    let mut parts = item.split(';').map(str::trim);
+    let name = parts.next().filter(|s| !s.is_empty())?;
+    let quality = parts
+        .find_map(|p| p.strip_prefix("q="))
+        .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+
+    Some((name, quality))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFERS: [Encoding; 3] = [Encoding::Brotli, Encoding::Gzip, Encoding::Deflate];
+
+    #[test]
+    fn negotiate_prefers_client_quality() {
+        let negotiate = |accept| Encoding::negotiate(&OFFERS, accept);
+        assert_eq!(negotiate("gzip, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("gzip;q=1, br;q=0.5"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("X-GZIP"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("br;q=0, *;q=0.1"), Some(Encoding::Gzip));
+    }
+
+    #[test]
+    fn negotiate_rejects_unacceptable() {
+        let negotiate = |accept| Encoding::negotiate(&OFFERS, accept);
+        assert_eq!(negotiate(""), None);
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate("zstd"), None);
+        assert_eq!(negotiate("*;q=0"), None);
+        assert_eq!(negotiate("gzip;q=abc"), None);
+    }
+
+    #[test]
+    fn compressible_types() {
+        let compression = Compression::default();
+        assert!(compression.is_compressible_type("text/html; charset=utf-8"));
+        assert!(compression.is_compressible_type("Application/JSON"));
+        assert!(!compression.is_compressible_type("image/png"));
+        assert!(!compression.is_compressible_type("text/event-stream; charset=utf-8"));
+    }
+
+    #[test]
+    fn apply_weakens_etag() {
+        let rsp = Response::string(StatusCode::OK, "a".repeat(2048))
+            .with_header(Names::ContentType.as_ref(), "text/plain")
+            .with_header(Names::ETag.as_ref(), "\"abc\"");
+        let rsp = Compression::default().apply(rsp, Some("gzip"));
+        assert_eq!(
+            rsp.headers.get(Names::ContentEncoding.as_ref()),
+            Some("gzip")
+        );
+        assert_eq!(rsp.headers.get(Names::ETag.as_ref()), Some("W/\"abc\""));
+    }
+
+    #[test]
+    fn apply_keeps_etag_of_identity() {
+        let rsp = Response::string(StatusCode::OK, "a".repeat(2048))
+            .with_header(Names::ContentType.as_ref(), "text/plain")
+            .with_header(Names::ETag.as_ref(), "\"abc\"");
+        let rsp = Compression::default().apply(rsp, Some("identity"));
+        assert_eq!(rsp.headers.get(Names::ContentEncoding.as_ref()), None);
+        assert_eq!(rsp.headers.get(Names::ETag.as_ref()), Some("\"abc\""));
+    }
+}
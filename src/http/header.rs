@@ -15,9 +15,99 @@ pub enum Names {
 
     #[strum(serialize = "content-type")]
     ContentType,
+
+    #[strum(serialize = "connection")]
+    Connection,
+
+    #[strum(serialize = "content-encoding")]
+    ContentEncoding,
+
+    #[strum(serialize = "accept")]
+    Accept,
+
+    #[strum(serialize = "accept-encoding")]
+    AcceptEncoding,
+
+    #[strum(serialize = "vary")]
+    Vary,
+
+    #[strum(serialize = "te")]
+    TE,
+
+    #[strum(serialize = "trailer")]
+    Trailer,
+
+    #[strum(serialize = "range")]
+    Range,
+
+    #[strum(serialize = "if-range")]
+    IfRange,
+
+    #[strum(serialize = "content-range")]
+    ContentRange,
+
+    #[strum(serialize = "accept-ranges")]
+    AcceptRanges,
+
+    #[strum(serialize = "etag")]
+    ETag,
+
+    #[strum(serialize = "last-modified")]
+    LastModified,
+
+    #[strum(serialize = "if-match")]
+    IfMatch,
+
+    #[strum(serialize = "if-none-match")]
+    IfNoneMatch,
+
+    #[strum(serialize = "if-modified-since")]
+    IfModifiedSince,
+
+    #[strum(serialize = "if-unmodified-since")]
+    IfUnmodifiedSince,
+
+    #[strum(serialize = "cache-control")]
+    CacheControl,
+
+    #[strum(serialize = "expires")]
+    Expires,
+
+    #[strum(serialize = "host")]
+    Host,
+
+    #[strum(serialize = "allow")]
+    Allow,
+
+    #[strum(serialize = "dav")]
+    DAV,
+
+    #[strum(serialize = "depth")]
+    Depth,
+
+    #[strum(serialize = "destination")]
+    Destination,
+
+    #[strum(serialize = "overwrite")]
+    Overwrite,
+
+    #[strum(serialize = "if")]
+    If,
+
+    #[strum(serialize = "lock-token")]
+    LockToken,
+
+    #[strum(serialize = "timeout")]
+    Timeout,
+
+    #[strum(serialize = "authorization")]
+    Authorization,
+
+    #[strum(serialize = "www-authenticate")]
+    WWWAuthenticate,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Headers(HashMap<String, String>);
 
 impl Headers {
@@ -33,16 +123,58 @@ impl Headers {
         self.0.insert(key.to_lowercase(), value.to_owned());
     }
 
+    /// Appends value to a comma-separated header, skipping values which are already present.
+    pub fn append(&mut self, key: &str, value: &str) {
+        let key = key.to_lowercase();
+        match self.0.get_mut(&key) {
+            Some(prev)
+                if prev
+                    .split(',')
+                    .any(|v| v.trim().eq_ignore_ascii_case(value)) => {}
+            Some(prev) => {
+                prev.push_str(", ");
+                prev.push_str(value);
+            }
+            None => {
+                self.0.insert(key, value.to_owned());
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.0.remove(key)
+    }
+
     pub fn has(&self, key: &str) -> bool {
         self.0.contains_key(key)
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn content_length(&self) -> Option<u64> {
         self.0
             .get("content-length")
             .and_then(|s| s.parse::<u64>().ok())
     }
 
+    /// Returns whether the body is sent using chunked transfer coding.
+    pub fn is_chunked(&self) -> bool {
+        self.get(Names::TransferEncoding.as_ref())
+            .and_then(|v| v.rsplit(',').next())
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case("chunked"))
+    }
+
+    /// Returns whether a comma-separated header contains a token, ignoring parameters.
+    pub fn contains_token(&self, key: &str, token: &str) -> bool {
+        self.get(key).is_some_and(|v| {
+            v.split(',')
+                .filter_map(|t| t.split(';').next())
+                .any(|t| t.trim().eq_ignore_ascii_case(token))
+        })
+    }
+
     pub fn set_content_length(&mut self, length: u64) {
         self.0.remove(Names::TransferEncoding.as_ref());
         self.0
@@ -0,0 +1,107 @@
+use super::{
+    header::Names, problem::Problem, request::Request, response::Response, status::StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::error::Category;
+use std::io::Read;
+use thiserror::Error;
+
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Max size of a JSON request body.
+pub const JSON_BODY_LIMIT: u64 = 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum JsonError {
+    #[error("expected {JSON_CONTENT_TYPE} content type")]
+    UnsupportedMediaType,
+    #[error("request body is larger than {JSON_BODY_LIMIT} bytes")]
+    TooLarge,
+    #[error("failed to read request body: {0}")]
+    ReadError(std::io::Error),
+    #[error("malformed JSON: {0}")]
+    Syntax(serde_json::Error),
+    #[error("invalid JSON payload: {0}")]
+    Data(serde_json::Error),
+}
+
+impl JsonError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UnsupportedMediaType => StatusCode::UnsupportedMediaType,
+            Self::TooLarge => StatusCode::PayloadTooLarge,
+            Self::ReadError(_) | Self::Syntax(_) => StatusCode::BadRequest,
+            Self::Data(_) => StatusCode::UnprocessableEntity,
+        }
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(err: serde_json::Error) -> Self {
+        match err.classify() {
+            Category::Data => Self::Data(err),
+            Category::Syntax | Category::Eof => Self::Syntax(err),
+            Category::Io => Self::ReadError(err.into()),
+        }
+    }
+}
+
+impl From<JsonError> for Response<'_> {
+    fn from(err: JsonError) -> Self {
+        Problem::new(err.status_code())
+            .with_detail(err)
+            .to_response(None)
+    }
+}
+
+impl Request<'_> {
+    /// Returns whether request has `application/json` or `+json` content type.
+    pub fn is_json(&self) -> bool {
+        self.headers
+            .get(Names::ContentType.as_ref())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_lowercase())
+            .is_some_and(|v| v == JSON_CONTENT_TYPE || v.ends_with("+json"))
+    }
+
+    /// Reads and deserializes JSON request body.
+    ///
+    /// Errors can be converted into a response with a matching status code.
+    pub fn json<T: DeserializeOwned>(&mut self) -> Result<T, JsonError> {
+        if !self.is_json() {
+            return Err(JsonError::UnsupportedMediaType);
+        }
+
+        if matches!(self.headers.content_length(), Some(len) if len > JSON_BODY_LIMIT) {
+            return Err(JsonError::TooLarge);
+        }
+
+        let mut body = Vec::new();
+        self.by_ref()
+            .take(JSON_BODY_LIMIT + 1)
+            .read_to_end(&mut body)
+            .map_err(JsonError::ReadError)?;
+
+        if body.len() as u64 > JSON_BODY_LIMIT {
+            return Err(JsonError::TooLarge);
+        }
+
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+impl<'a> Response<'a> {
+    /// Creates a response with serialized JSON body.
+    pub fn json<T: Serialize + ?Sized>(status_code: StatusCode, value: &T) -> Response<'a> {
+        match serde_json::to_vec(value) {
+            Ok(body) => Response::new(status_code)
+                .with_content_type(JSON_CONTENT_TYPE.to_owned())
+                .with_content_length(body.len() as u64)
+                .with_body(body),
+            Err(err) => Response::string(
+                StatusCode::InternalServerError,
+                format!("failed to serialize response: {err}"),
+            ),
+        }
+    }
+}
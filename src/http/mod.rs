@@ -1,16 +1,41 @@
 // export { Server, Method, ParseError } from './server';
+#[allow(unused_imports)]
+pub use body::{Body, BodySender, Chunk};
+pub use compress::{Compression, Encoding};
+#[allow(unused_imports)]
 pub use header::{Headers, Names as HeaderNames};
+#[cfg(feature = "serde")]
+#[allow(unused_imports)]
+pub use json::{JsonError, JSON_BODY_LIMIT};
+pub use problem::Problem;
+#[allow(unused_imports)]
 pub use query_string::{QueryParam, QueryString};
-pub use request::{Method, ParseError, Request};
+pub use request::{Method, ParseError, Request, Version};
 pub use response::*;
+#[allow(unused_imports)]
+pub use sendfile::SendFile;
 pub use server::*;
+#[allow(unused_imports)]
+pub use sse::{Disconnected, Event, EventSender};
 pub use status::*;
+#[allow(unused_imports)]
+pub use url::{decode_path, DecodeError};
 
 // import * as server from './server';
+pub mod accept;
+mod body;
+mod chunked;
+mod compress;
+pub mod escape;
 mod header;
+#[cfg(feature = "serde")]
+mod json;
+mod problem;
 mod query_string;
 mod response;
+mod sendfile;
 mod server;
+mod sse;
 mod status;
 mod url;
 
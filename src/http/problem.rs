@@ -0,0 +1,139 @@
+use super::{accept, escape, request::ParseError, response::Response, status::StatusCode};
+use std::fmt::Write;
+
+const PROBLEM_JSON: &str = "application/problem+json";
+const JSON: &str = "application/json";
+const HTML: &str = "text/html";
+const PLAIN_TEXT: &str = "text/plain";
+
+/// Problem details object as defined by RFC 9457.
+#[derive(Debug)]
+pub struct Problem {
+    pub status: StatusCode,
+
+    /// URI reference identifying the problem type.
+    pub type_uri: String,
+
+    /// Short human-readable summary of the problem type.
+    pub title: String,
+
+    /// Explanation specific to this occurrence of the problem.
+    pub detail: Option<String>,
+
+    /// URI reference identifying this occurrence of the problem.
+    pub instance: Option<String>,
+
+    /// Extension members. Values are rendered as JSON strings.
+    pub extensions: Vec<(String, String)>,
+}
+
+impl Problem {
+    /// Creates a problem with `about:blank` type and a title matching the status code.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            type_uri: String::from("about:blank"),
+            title: status.phrase().to_owned(),
+            detail: None,
+            instance: None,
+            extensions: Vec::new(),
+        }
+    }
+
+    pub fn with_type(mut self, type_uri: &str) -> Self {
+        self.type_uri = type_uri.to_owned();
+        self
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl ToString) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    pub fn with_instance(mut self, instance: &str) -> Self {
+        self.instance = Some(instance.to_owned());
+        self
+    }
+
+    pub fn with_extension(mut self, key: &str, value: impl ToString) -> Self {
+        self.extensions.push((key.to_owned(), value.to_string()));
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        let _ = write!(
+            out,
+            "\"type\":{},\"title\":{},\"status\":{}",
+            escape::json(&self.type_uri),
+            escape::json(&self.title),
+            self.status
+        );
+
+        let members = [("detail", &self.detail), ("instance", &self.instance)];
+        for (key, value) in members {
+            if let Some(value) = value {
+                let _ = write!(out, ",\"{key}\":{}", escape::json(value));
+            }
+        }
+
+        for (key, value) in self.extensions.iter() {
+            let _ = write!(out, ",{}:{}", escape::json(key), escape::json(value));
+        }
+
+        out.push('}');
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = escape::html(&self.title);
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html><head><title>{} {title}</title></head><body>\n<h1>{title}</h1>",
+            self.status
+        );
+
+        if let Some(ref detail) = self.detail {
+            let _ = write!(out, "\n<p>{}</p>", escape::html(detail));
+        }
+        out.push_str("\n</body></html>");
+        out
+    }
+
+    pub fn to_text(&self) -> String {
+        match self.detail {
+            Some(ref detail) => format!("{} {}\n{detail}\n", self.status, self.title),
+            None => format!("{} {}\n", self.status, self.title),
+        }
+    }
+
+    /// Renders problem using a format preferred by a client's `Accept` header.
+    ///
+    /// JSON is used if header is missing or no format is acceptable.
+    pub fn to_response<'a>(&self, accept: Option<&str>) -> Response<'a> {
+        let format = accept
+            .and_then(|v| accept::preferred(v, &[PROBLEM_JSON, JSON, HTML, PLAIN_TEXT]))
+            .unwrap_or(PROBLEM_JSON);
+
+        let (content_type, body) = match format {
+            HTML => ("text/html; charset=utf-8", self.to_html()),
+            PLAIN_TEXT => ("text/plain; charset=utf-8", self.to_text()),
+            _ => (PROBLEM_JSON, self.to_json()),
+        };
+
+        Response::new(self.status)
+            .with_content_type(content_type.to_owned())
+            .with_content_length(body.len() as u64)
+            .with_body(body)
+    }
+}
+
+impl From<&ParseError> for Problem {
+    fn from(err: &ParseError) -> Self {
+        Problem::new(err.status_code()).with_detail(err)
+    }
+}
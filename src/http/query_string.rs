@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
 
 #[derive(Debug)]
 pub enum QueryParam {
@@ -12,11 +13,21 @@ pub struct QueryString {
 }
 
 impl QueryString {
-    fn get(&self, key: &str) -> Option<&QueryParam> {
+    pub fn get(&self, key: &str) -> Option<&QueryParam> {
         self.items.get(key)
     }
 
-    pub fn to_string(&self) -> String {
+    /// Returns the first value of a parameter.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            QueryParam::Single(v) => Some(v),
+            QueryParam::Multiple(values) => values.first().map(String::as_str),
+        }
+    }
+}
+
+impl Display for QueryString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         // TODO: use FP approach
         let mut str = String::new();
         str.push('?');
@@ -38,7 +49,7 @@ impl QueryString {
                 }
             }
         }
-        return str;
+        write!(f, "{str}")
     }
 }
 
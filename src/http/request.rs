@@ -1,16 +1,19 @@
-use super::header::Headers;
+use super::chunked::ChunkedReader;
+use super::header::{Headers, Names as HeaderNames};
 use super::query_string::QueryString;
+use super::status::StatusCode;
 use super::url::URL;
 use std::convert::{From, TryFrom};
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
-use std::io::{Cursor, Read};
+use std::io::{BufReader, Cursor, Read};
 use std::str::FromStr;
 use thiserror::Error;
 
 const READ_BUFFER_SIZE: usize = 1024;
 const REQUEST_DELIMITER: &[u8; 4] = b"\r\n\r\n";
 const REQUEST_DELIMITER_LEN: usize = REQUEST_DELIMITER.len();
-const READ_LIMIT: usize = 32 * 1024;
+/// Largest accepted header section, also used for trailers of chunked bodies.
+pub(super) const READ_LIMIT: usize = 32 * 1024;
 
 #[derive(Error, Debug)]
 pub enum ParseError {
@@ -30,6 +33,20 @@ pub enum ParseError {
     RequestTooBig,
 }
 
+impl ParseError {
+    /// Returns status code of response to a request which can't be parsed.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidMethod => StatusCode::NotImplemented,
+            Self::InvalidProtocol => StatusCode::HTTPVersionNotSupported,
+            Self::RequestTooBig => StatusCode::RequestHeaderFieldsTooLarge,
+            Self::ReadError | Self::InvalidRequest | Self::InvalidEncoding | Self::MissingBody => {
+                StatusCode::BadRequest
+            }
+        }
+    }
+}
+
 impl From<InvalidMethod> for ParseError {
     fn from(_: InvalidMethod) -> Self {
         Self::InvalidMethod
@@ -42,7 +59,7 @@ impl From<std::str::Utf8Error> for ParseError {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum Method {
     GET,
     POST,
@@ -53,6 +70,15 @@ pub enum Method {
     CONNECT,
     TRACE,
     PATCH,
+
+    // WebDAV methods (RFC 4918)
+    PROPFIND,
+    PROPPATCH,
+    MKCOL,
+    COPY,
+    MOVE,
+    LOCK,
+    UNLOCK,
 }
 
 pub struct InvalidMethod;
@@ -71,6 +97,13 @@ impl FromStr for Method {
             "CONNECT" => Ok(Method::CONNECT),
             "TRACE" => Ok(Method::TRACE),
             "PATCH" => Ok(Method::PATCH),
+            "PROPFIND" => Ok(Method::PROPFIND),
+            "PROPPATCH" => Ok(Method::PROPPATCH),
+            "MKCOL" => Ok(Method::MKCOL),
+            "COPY" => Ok(Method::COPY),
+            "MOVE" => Ok(Method::MOVE),
+            "LOCK" => Ok(Method::LOCK),
+            "UNLOCK" => Ok(Method::UNLOCK),
             _ => Err(InvalidMethod),
         }
     }
@@ -91,17 +124,61 @@ impl Display for Method {
                 Self::CONNECT => "CONNECT",
                 Self::TRACE => "TRACE",
                 Self::PATCH => "PATCH",
+                Self::PROPFIND => "PROPFIND",
+                Self::PROPPATCH => "PROPPATCH",
+                Self::MKCOL => "MKCOL",
+                Self::COPY => "COPY",
+                Self::MOVE => "MOVE",
+                Self::LOCK => "LOCK",
+                Self::UNLOCK => "UNLOCK",
             }
         )
     }
 }
 
+/// HTTP protocol version sent by a client in the request line.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Version {
+    HTTP10,
+    HTTP11,
+}
+
+impl FromStr for Version {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "HTTP/1.0" => Ok(Version::HTTP10),
+            "HTTP/1.1" => Ok(Version::HTTP11),
+            _ => Err(ParseError::InvalidProtocol),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::HTTP10 => write!(f, "HTTP/1.0"),
+            Self::HTTP11 => write!(f, "HTTP/1.1"),
+        }
+    }
+}
+
 pub struct Request<'a> {
     pub method: Method,
+    pub version: Version,
     pub url: URL,
     pub headers: Headers,
 
-    body: Option<Box<dyn Read + 'a>>,
+    body: Option<RequestBody<'a>>,
+}
+
+enum RequestBody<'a> {
+    /// Body with length specified by `Content-Length` header.
+    Fixed(Box<dyn Read + 'a>),
+
+    /// Body sent using `Transfer-Encoding: chunked`.
+    Chunked(ChunkedReader<BufReader<Box<dyn Read + 'a>>>),
 }
 
 impl<'a> Request<'a> {
@@ -114,34 +191,56 @@ impl<'a> Request<'a> {
         self.url.query.as_ref()
     }
 
-    pub fn from_reader(reader: &'a mut dyn Read) -> Result<Request, ParseError> {
+    /// Returns trailer fields sent after a chunked request body.
+    ///
+    /// Trailers are available only after the body is read to the end.
+    pub fn trailers(&self) -> Option<&Headers> {
+        match self.body {
+            Some(RequestBody::Chunked(ref body)) => body.trailers(),
+            _ => None,
+        }
+    }
+
+    pub fn from_reader(reader: &'a mut dyn Read) -> Result<Request<'a>, ParseError> {
         // Consume HTTP request until find payload delimiter.
         let (buff, body_offset) = read_until_payload(reader)?;
 
         let header_str = std::str::from_utf8(&buff[0..body_offset])?;
-        let (url, method, offset) = parse_proto(header_str)?;
+        let (url, method, version, offset) = parse_proto(header_str)?;
 
         // Collect http headers until request body starts
         let headers = Headers::from(&header_str[offset..]);
 
-        let body = match method {
-            Method::POST | Method::PUT | Method::PATCH => {
-                // Combine consumed request payload part with reader.
-                // We don't support "Transfer-Encoding: Chunked" yet.
-                let content_length = headers.content_length().ok_or(ParseError::InvalidRequest)?;
-                let payload_remainder = buff[body_offset..].to_vec();
-                let remaiming_length = content_length - (payload_remainder.len() as u64);
-
-                let body: Box<dyn Read> =
-                    Box::new(Cursor::new(payload_remainder).chain(reader.take(remaiming_length)));
-                Some(body)
+        // Body is required for methods which always send content, others (e.g. PROPFIND)
+        // may send it if framing headers are present.
+        let requires_body = matches!(method, Method::POST | Method::PUT | Method::PATCH);
+        let payload_remainder = buff[body_offset..].to_vec();
+        let body = if headers.has(HeaderNames::TransferEncoding.as_ref()) {
+            // Length of a body without final chunked coding can't be determined.
+            if !headers.is_chunked() {
+                return Err(ParseError::InvalidRequest);
             }
-            _ => None,
+
+            let body: Box<dyn Read> = Box::new(Cursor::new(payload_remainder).chain(reader));
+            Some(RequestBody::Chunked(ChunkedReader::new(BufReader::new(
+                body,
+            ))))
+        } else if let Some(content_length) = headers.content_length() {
+            let remaiming_length = content_length.saturating_sub(payload_remainder.len() as u64);
+
+            let body: Box<dyn Read> =
+                Box::new(Cursor::new(payload_remainder).chain(reader.take(remaiming_length)));
+            Some(RequestBody::Fixed(body))
+        } else if requires_body {
+            return Err(ParseError::InvalidRequest);
+        } else {
+            None
         };
 
         Ok(Request {
             url,
             method,
+            version,
             headers,
             body,
         })
@@ -156,8 +255,9 @@ fn find_body_delimiter(buf: &[u8]) -> Option<usize> {
 impl Read for Request<'_> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         match self.body {
-            Some(ref mut body) => body.read(buf),
-            _ => Ok(0),
+            Some(RequestBody::Fixed(ref mut body)) => body.read(buf),
+            Some(RequestBody::Chunked(ref mut body)) => body.read(buf),
+            None => Ok(0),
         }
     }
 }
@@ -211,28 +311,25 @@ fn read_until_payload(src: &mut dyn Read) -> Result<(Vec<u8>, usize), ParseError
     }
 }
 
-fn parse_proto(src: &str) -> Result<(URL, Method, usize), ParseError> {
-    let head_end = src.find('\r').ok_or_else(|| ParseError::InvalidRequest)?;
+fn parse_proto(src: &str) -> Result<(URL, Method, Version, usize), ParseError> {
+    let head_end = src.find('\r').ok_or(ParseError::InvalidRequest)?;
     let chunks: Vec<&str> = src[..head_end].splitn(3, ' ').collect();
     if chunks.len() != 3 {
         return Err(ParseError::InvalidRequest);
     }
 
     let (method, path, protocol) = (chunks[0], chunks[1], chunks[2]);
-    if protocol != "HTTP/1.1" {
-        return Err(ParseError::InvalidProtocol);
-    }
-
+    let version: Version = protocol.parse()?;
     let method: Method = method.parse()?;
     let url = URL::from(path);
-    Ok((url, method, head_end))
+    Ok((url, method, version, head_end))
 }
 
 fn get_next_word(src: &str) -> Option<(&str, usize)> {
     if src.is_empty() {
         return None;
     }
-    for (i, char) in src.chars().enumerate() {
+    for (i, char) in src.char_indices() {
         if char.is_whitespace() {
             return Some((&src[..i], i + 1));
         }
@@ -240,3 +337,32 @@ fn get_next_word(src: &str) -> Option<(&str, usize)> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_body_without_final_chunked_coding() {
+        for coding in ["gzip", "chunked, gzip"] {
+            let raw = format!(
+                "PUT / HTTP/1.1\r\nTransfer-Encoding: {coding}\r\nContent-Length: 1\r\n\r\na"
+            );
+            let mut src = raw.as_bytes();
+            assert!(matches!(
+                Request::from_reader(&mut src),
+                Err(ParseError::InvalidRequest)
+            ));
+        }
+    }
+
+    #[test]
+    fn decodes_chunked_body() {
+        let raw = "PUT / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n1\r\na\r\n0\r\n\r\n";
+        let mut src = raw.as_bytes();
+        let mut req = Request::from_reader(&mut src).unwrap();
+        let mut body = String::new();
+        req.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "a");
+    }
+}
@@ -1,21 +1,62 @@
-use super::{header::Headers, header::Names, status::StatusCode};
+use super::{
+    body::Body, chunked::ChunkedWriter, header::Headers, header::Names, request::Version,
+    sendfile::SendFile, status::StatusCode,
+};
 use std::convert::AsRef;
 use std::error::Error;
-use std::io::{self, Cursor, Read, Write};
+use std::io::{self, Write};
+use std::mem;
 use std::string::ToString;
 
+/// Describes how the end of a response body is communicated to a client.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Framing {
+    /// Body size is known from `Content-Length` header.
+    Length,
+
+    /// Body is sent using `Transfer-Encoding: chunked`.
+    Chunked,
+
+    /// Body ends when connection is closed (HTTP/1.0 clients).
+    Close,
+}
+
+/// Trailer fields sent after a chunked response body.
+pub struct Trailers<'a> {
+    names: Vec<String>,
+    values: Box<dyn FnOnce() -> Headers + 'a>,
+}
+
+impl Trailers<'_> {
+    /// Computes trailer values, keeping only fields declared in `Trailer` header.
+    fn collect(self) -> Headers {
+        let values = (self.values)();
+        let mut headers = Headers::new();
+        for name in self.names.iter() {
+            if let Some(value) = values.get(name) {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+}
+
 pub struct Response<'a> {
     pub status_code: StatusCode,
-    pub body: Option<Box<dyn Read + 'a>>,
+    pub body: Body<'a>,
     pub headers: Headers,
+
+    /// Trailer fields. Server drops them if a client didn't send `TE: trailers`.
+    pub trailers: Option<Trailers<'a>>,
 }
 
 impl<'a> Response<'a> {
     pub fn new(status_code: StatusCode) -> Response<'a> {
         Response {
             status_code,
-            body: None,
+            body: Body::Empty,
             headers: Headers::new(),
+            trailers: None,
         }
     }
 
@@ -26,7 +67,8 @@ impl<'a> Response<'a> {
         Response {
             status_code,
             headers,
-            body: Some(Box::new(Cursor::new(body.into_bytes()))),
+            body: Body::from(body),
+            trailers: None,
         }
     }
 
@@ -34,8 +76,19 @@ impl<'a> Response<'a> {
         Self::string(status_code, err.to_string())
     }
 
-    pub fn with_body(mut self, body: Box<dyn Read + 'a>) -> Response<'a> {
-        self.body = Some(body);
+    pub fn with_body(mut self, body: impl Into<Body<'a>>) -> Response<'a> {
+        self.body = body.into();
+        self
+    }
+
+    /// Declares trailer fields which are computed after the body is sent.
+    ///
+    /// Response with trailers is always sent using chunked encoding.
+    pub fn with_trailers(mut self, names: &[&str], values: impl FnOnce() -> Headers + 'a) -> Self {
+        self.trailers = Some(Trailers {
+            names: names.iter().map(|n| n.to_lowercase()).collect(),
+            values: Box::new(values),
+        });
         self
     }
 
@@ -55,13 +108,49 @@ impl<'a> Response<'a> {
         self
     }
 
-    pub fn send(&mut self, stream: &mut impl Write) -> std::io::Result<()> {
-        let is_chunked = self.body.is_some() && !self.headers.has(Names::ContentLength.as_ref());
+    fn framing(&mut self, version: Version) -> Framing {
+        if self.body.is_empty() {
+            return Framing::Length;
+        }
+
+        // Trailers can be sent only after the last chunk.
+        if self.trailers.is_some() && version == Version::HTTP11 {
+            self.headers.remove(Names::ContentLength.as_ref());
+            return Framing::Chunked;
+        }
 
-        // Write request in chunked mode if content length isn't specified
-        if is_chunked {
-            self.headers
-                .add(Names::TransferEncoding.to_string(), String::from("chunked"));
+        if self.headers.has(Names::ContentLength.as_ref()) {
+            return Framing::Length;
+        }
+
+        if let Some(len) = self.body.len() {
+            self.headers.set_content_length(len);
+            return Framing::Length;
+        }
+
+        // HTTP/1.0 clients don't understand chunked encoding.
+        match version {
+            Version::HTTP10 => Framing::Close,
+            Version::HTTP11 => Framing::Chunked,
+        }
+    }
+
+    fn write_head(&mut self, stream: &mut impl Write, framing: Framing) -> io::Result<()> {
+        match self.trailers {
+            Some(ref trailers) if framing == Framing::Chunked => self
+                .headers
+                .add(Names::Trailer.to_string(), trailers.names.join(", ")),
+            _ => self.trailers = None,
+        }
+
+        match framing {
+            Framing::Chunked => self
+                .headers
+                .add(Names::TransferEncoding.to_string(), String::from("chunked")),
+            Framing::Close => self
+                .headers
+                .add(Names::Connection.to_string(), String::from("close")),
+            Framing::Length => {}
         }
 
         write!(
@@ -71,16 +160,92 @@ impl<'a> Response<'a> {
             self.status_code.phrase(),
         )?;
         self.headers.send(stream)?;
+        write!(stream, "\r\n\r\n")
+    }
 
-        write!(stream, "\r\n\r\n")?;
-        if let Some(ref mut body) = self.body {
-            io::copy(body, stream)?;
-        }
+    /// Writes response to a stream using body framing supported by a client's HTTP version.
+    ///
+    /// File bodies are transferred using [SendFile] when body isn't chunked.
+    pub fn send(&mut self, stream: &mut impl SendFile, version: Version) -> io::Result<()> {
+        let framing = self.framing(version);
+        self.write_head(stream, framing)?;
 
-        if is_chunked {
-            write!(stream, "\r\n\r\n")?;
+        let body = mem::take(&mut self.body);
+        if framing == Framing::Chunked {
+            let mut writer = ChunkedWriter::new(stream);
+            body.write_to(&mut writer)?;
+
+            let trailers = self.trailers.take().map(Trailers::collect);
+            writer.finish(trailers.as_ref())?;
+        } else {
+            body.send_to(stream)?;
         }
 
         Ok(())
     }
+
+    /// Writes response status and headers for a `HEAD` request.
+    ///
+    /// Headers are the same as for [Response::send] but body is dropped without being read.
+    pub fn send_head(&mut self, stream: &mut impl Write, version: Version) -> io::Result<()> {
+        let framing = self.framing(version);
+        self.write_head(stream, framing)?;
+        self.body = Body::Empty;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Chunk;
+
+    impl SendFile for Vec<u8> {}
+
+    /// Splits sent response into lowercase header lines and body.
+    fn split(out: &[u8]) -> (Vec<String>, Vec<u8>) {
+        let end = out.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8_lossy(&out[..end]).to_lowercase();
+        (
+            head.lines().map(str::to_owned).collect(),
+            out[end + 4..].to_vec(),
+        )
+    }
+
+    #[test]
+    fn head_has_same_headers_as_get() {
+        let mut get = Vec::new();
+        Response::string(StatusCode::OK, "hello".to_owned())
+            .send(&mut get, Version::HTTP11)
+            .unwrap();
+        let mut head = Vec::new();
+        Response::string(StatusCode::OK, "hello".to_owned())
+            .send_head(&mut head, Version::HTTP11)
+            .unwrap();
+
+        let (get_headers, get_body) = split(&get);
+        let (mut head_headers, head_body) = split(&head);
+        assert_eq!(get_body, b"hello");
+        assert!(head_body.is_empty());
+        assert!(head_headers.contains(&"content-length: 5".to_owned()));
+
+        let mut get_headers = get_headers;
+        get_headers.sort();
+        head_headers.sort();
+        assert_eq!(get_headers, head_headers);
+    }
+
+    #[test]
+    fn head_of_stream_is_chunked_without_body() {
+        let stream = Body::stream(std::iter::once(Ok(Chunk::Data(b"data".to_vec()))));
+        let mut head = Vec::new();
+        Response::new(StatusCode::OK)
+            .with_body(stream)
+            .send_head(&mut head, Version::HTTP11)
+            .unwrap();
+
+        let (headers, body) = split(&head);
+        assert!(headers.contains(&"transfer-encoding: chunked".to_owned()));
+        assert!(body.is_empty());
+    }
 }
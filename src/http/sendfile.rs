@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::net::TcpStream;
+
+/// Writer which can transfer file contents without copying them through userspace.
+///
+/// Default implementation copies file data using regular reads and writes.
+pub trait SendFile: Write {
+    /// Writes `len` bytes of a file starting at `offset`.
+    fn send_file(&mut self, mut file: &File, offset: u64, len: u64) -> io::Result<u64> {
+        file.seek(SeekFrom::Start(offset))?;
+        copy_exact(&mut file.take(len), self, len)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl SendFile for TcpStream {}
+
+#[cfg(target_os = "linux")]
+impl SendFile for TcpStream {
+    fn send_file(&mut self, file: &File, offset: u64, len: u64) -> io::Result<u64> {
+        use std::os::fd::AsRawFd;
+
+        // Max chunk size accepted by sendfile(2) in a single call.
+        const MAX_CHUNK: u64 = 0x7ffff000;
+
+        let mut off = offset as libc::off_t;
+        let mut remaining = len;
+        while remaining > 0 {
+            let count = remaining.min(MAX_CHUNK) as usize;
+            let ret =
+                unsafe { libc::sendfile(self.as_raw_fd(), file.as_raw_fd(), &mut off, count) };
+            match ret {
+                -1 => {
+                    let err = io::Error::last_os_error();
+                    match err.raw_os_error() {
+                        Some(libc::EINTR) => continue,
+                        // Syscall isn't supported for this file, nothing was sent yet.
+                        Some(libc::EINVAL | libc::ENOSYS) if remaining == len => {
+                            let mut file = file;
+                            file.seek(SeekFrom::Start(offset))?;
+                            return copy_exact(&mut file.take(len), self, len);
+                        }
+                        _ => return Err(err),
+                    }
+                }
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => remaining -= n as u64,
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+/// Copies data and checks that source wasn't truncated while being sent.
+fn copy_exact<W: Write + ?Sized>(src: &mut impl Read, dst: &mut W, len: u64) -> io::Result<u64> {
+    match io::copy(src, dst)? {
+        n if n == len => Ok(n),
+        _ => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
@@ -1,33 +1,54 @@
-use super::ParseError;
+use super::{Compression, HeaderNames, Method, ParseError, Version};
 use crate::http::{Request, Response};
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::thread;
 
 pub trait Handler: Send + Sync {
     fn handle_request<'a, 'b>(&self, req: Request<'a>) -> Response<'b>;
-    fn handle_bad_request(&self, err: &ParseError) -> Response;
+
+    /// Handles `HEAD` request by running `GET` handler.
+    ///
+    /// Server sends only headers of returned response, body is discarded.
+    fn handle_head<'a, 'b>(&self, mut req: Request<'a>) -> Response<'b> {
+        req.method = Method::GET;
+        self.handle_request(req)
+    }
+
+    fn handle_bad_request(&self, err: &ParseError) -> Response<'_>;
 }
 
 pub struct Server<'a> {
     address: String,
     handler: &'a dyn Handler,
+    compression: Option<Compression>,
 }
 
 impl<'a> Server<'a> {
     pub fn new(address: String, handler: &'a dyn Handler) -> Self {
-        Self { address, handler }
+        Self {
+            address,
+            handler,
+            compression: None,
+        }
+    }
+
+    /// Enables on-the-fly compression of responses.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
     }
 
     pub fn start(&'a self) {
         let listener = TcpListener::bind(&self.address).unwrap();
         println!("Server is running on {}", self.address);
-        loop {
+
+        // Connections are handled concurrently, long-lived ones (e.g. event streams)
+        // don't block other clients. Scope waits for all connections before returning.
+        thread::scope(|scope| loop {
             match listener.accept() {
                 Ok((stream, addr)) => {
-                    thread::scope(|scope| {
-                        scope.spawn(move || {
-                            self.handle_request(stream, addr);
-                        });
+                    scope.spawn(move || {
+                        self.handle_request(stream, addr);
                     });
                 }
                 Err(err) => {
@@ -36,18 +57,44 @@ impl<'a> Server<'a> {
                     return;
                 }
             }
-        }
+        });
     }
 
     fn handle_request(&self, mut stream: TcpStream, addr: SocketAddr) {
-        let mut rsp = match Request::from_reader(&mut stream) {
+        let (mut rsp, method, version) = match Request::from_reader(&mut stream) {
             Ok(req) => {
                 Self::log_request(&req, &addr);
-                self.handler.handle_request(req)
+                let (method, version) = (req.method, req.version);
+                let accept_encoding = req
+                    .headers
+                    .get(HeaderNames::AcceptEncoding.as_ref())
+                    .map(str::to_owned);
+                let accepts_trailers = req
+                    .headers
+                    .contains_token(HeaderNames::TE.as_ref(), "trailers");
+
+                let rsp = match method {
+                    Method::HEAD => self.handler.handle_head(req),
+                    _ => self.handler.handle_request(req),
+                };
+
+                let mut rsp = match self.compression {
+                    Some(ref compression) => compression.apply(rsp, accept_encoding.as_deref()),
+                    None => rsp,
+                };
+
+                if !accepts_trailers {
+                    rsp.trailers = None;
+                }
+                (rsp, method, version)
             }
             Err(err) => {
                 println!("{addr}: can't parse request - {err}");
-                self.handler.handle_bad_request(&err)
+                (
+                    self.handler.handle_bad_request(&err),
+                    Method::GET,
+                    Version::HTTP11,
+                )
             }
         };
 
@@ -56,7 +103,11 @@ impl<'a> Server<'a> {
         rsp.headers.insert("X-Powered-By", "PHP/5.3.0");
 
         println!("{}", rsp.status_code);
-        if let Err(err) = rsp.send(&mut stream) {
+        let result = match method {
+            Method::HEAD => rsp.send_head(&mut stream, version),
+            _ => rsp.send(&mut stream, version),
+        };
+        if let Err(err) = result {
             println!("{addr}: failed to send response - {err}")
         }
     }
@@ -70,3 +121,29 @@ impl<'a> Server<'a> {
         println!("[{}] {} {}{}", addr, req.method, req.path(), &query_params,);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+
+    struct Echo;
+
+    impl Handler for Echo {
+        fn handle_request<'a, 'b>(&self, req: Request<'a>) -> Response<'b> {
+            Response::string(StatusCode::OK, req.method.to_string())
+        }
+
+        fn handle_bad_request(&self, _: &ParseError) -> Response<'_> {
+            Response::new(StatusCode::BadRequest)
+        }
+    }
+
+    #[test]
+    fn head_runs_get_handler() {
+        let mut src: &[u8] = b"HEAD /file HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let req = Request::from_reader(&mut src).unwrap();
+        let rsp = Echo.handle_head(req);
+        assert_eq!(rsp.headers.content_length(), Some(3));
+    }
+}
@@ -0,0 +1,188 @@
+use super::{
+    body::{Body, Chunk},
+    header::Names as HeaderNames,
+    request::Request,
+    response::Response,
+    status::StatusCode,
+};
+use std::io;
+use std::mem;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+const LAST_EVENT_ID: &str = "last-event-id";
+const KEEP_ALIVE_COMMENT: &[u8] = b": keep-alive\n\n";
+
+/// Single Server-Sent Events message.
+#[derive(Debug, Default, Clone)]
+pub struct Event {
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+    pub retry: Option<Duration>,
+}
+
+impl Event {
+    pub fn new(data: impl ToString) -> Self {
+        Self {
+            data: data.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_event(mut self, event: &str) -> Self {
+        self.event = Some(event.to_owned());
+        self
+    }
+
+    pub fn with_id(mut self, id: impl ToString) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// Sets reconnection time used by a client.
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Encodes event into `text/event-stream` frame.
+    pub fn encode(&self) -> Vec<u8> {
+        // Line breaks would terminate a field, so they're stripped from single-line fields.
+        let single_line = |v: &str| v.replace(['\r', '\n'], "");
+
+        let mut out = String::new();
+        if let Some(ref event) = self.event {
+            out.push_str(&format!("event: {}\n", single_line(event)));
+        }
+
+        if let Some(ref id) = self.id {
+            out.push_str(&format!("id: {}\n", single_line(id)));
+        }
+
+        if let Some(retry) = self.retry {
+            out.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+
+        // Each of CRLF, CR and LF ends a line, so every line of data gets its own field.
+        if self.data.is_empty() {
+            out.push_str("data:\n");
+        } else {
+            for line in self.data.split("\r\n").flat_map(|l| l.split(['\r', '\n'])) {
+                out.push_str(&format!("data: {line}\n"));
+            }
+        }
+
+        out.push('\n');
+        out.into_bytes()
+    }
+}
+
+/// Returned when event can't be sent because client has disconnected.
+#[derive(Debug)]
+pub struct Disconnected;
+
+/// Sending half of an event stream. Can be moved to another thread.
+///
+/// Stream is closed when sender is dropped.
+#[derive(Clone)]
+pub struct EventSender {
+    tx: Sender<Event>,
+}
+
+impl EventSender {
+    /// Sends event to a client. Fails once the client has disconnected.
+    pub fn send(&self, event: Event) -> Result<(), Disconnected> {
+        self.tx.send(event).map_err(|_| Disconnected)
+    }
+}
+
+impl<'a> Response<'a> {
+    /// Creates `text/event-stream` response which sends events produced by returned [EventSender].
+    ///
+    /// Keep-alive comment is sent when no events were produced during `keep_alive` interval.
+    /// It also allows to detect disconnected clients when there are no events.
+    pub fn event_stream(keep_alive: Duration) -> (EventSender, Response<'a>) {
+        let (tx, rx) = mpsc::channel();
+        let body = Body::stream(EventStream {
+            rx,
+            keep_alive,
+            pending_flush: false,
+        });
+
+        let rsp = Response::new(StatusCode::OK)
+            .with_content_type("text/event-stream".to_owned())
+            .with_header(HeaderNames::CacheControl.as_ref(), "no-cache")
+            .with_body(body);
+
+        (EventSender { tx }, rsp)
+    }
+}
+
+impl Request<'_> {
+    /// Returns ID of the last event received by a reconnecting client.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.headers.get(LAST_EVENT_ID)
+    }
+}
+
+/// Converts events into body chunks, flushing each event.
+struct EventStream {
+    rx: Receiver<Event>,
+    keep_alive: Duration,
+    pending_flush: bool,
+}
+
+impl Iterator for EventStream {
+    type Item = io::Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if mem::take(&mut self.pending_flush) {
+            return Some(Ok(Chunk::Flush));
+        }
+
+        let frame = match self.rx.recv_timeout(self.keep_alive) {
+            Ok(event) => event.encode(),
+            Err(RecvTimeoutError::Timeout) => KEEP_ALIVE_COMMENT.to_vec(),
+            Err(RecvTimeoutError::Disconnected) => return None,
+        };
+
+        self.pending_flush = true;
+        Some(Ok(Chunk::Data(frame)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(event: Event) -> String {
+        String::from_utf8(event.encode()).unwrap()
+    }
+
+    #[test]
+    fn data_lines_are_split_on_any_line_break() {
+        assert_eq!(encode(Event::new("")), "data:\n\n");
+        assert_eq!(
+            encode(Event::new("a\r\nb\nc")),
+            "data: a\ndata: b\ndata: c\n\n"
+        );
+        assert_eq!(
+            encode(Event::new("a\revent: x\rid: 1")),
+            "data: a\ndata: event: x\ndata: id: 1\n\n"
+        );
+        assert_eq!(encode(Event::new("a\n")), "data: a\ndata: \n\n");
+    }
+
+    #[test]
+    fn single_line_fields_are_sanitized() {
+        let event = Event::new("d")
+            .with_event("a\rdata: x")
+            .with_id("1\r\n2\n")
+            .with_retry(Duration::from_secs(1));
+        assert_eq!(
+            encode(event),
+            "event: adata: x\nid: 12\nretry: 1000\ndata: d\n\n"
+        );
+    }
+}
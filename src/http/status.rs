@@ -48,6 +48,7 @@ pub enum StatusCode {
     MultiStatus = 207,
     IMUsed = 226,
     PermanentRedirect = 308,
+    MisdirectedRequest = 421,
     UnprocessableEntity = 422,
     Locked = 423,
     FailedDependency = 424,
@@ -115,6 +116,7 @@ impl StatusCode {
             Self::MultiStatus => "Multi-Status",
             Self::IMUsed => "IM Used",
             Self::PermanentRedirect => "Permanent Redirect",
+            Self::MisdirectedRequest => "Misdirected Request",
             Self::UnprocessableEntity => "Unprocessable Entity",
             Self::Locked => "Locked",
             Self::FailedDependency => "Failed Dependency",
@@ -1,4 +1,5 @@
 use super::query_string::QueryString;
+use thiserror::Error;
 
 pub struct URL {
     pub path: String,
@@ -15,3 +16,76 @@ impl From<&str> for URL {
         Self { path, query }
     }
 }
+
+/// Error of percent-decoding a request path.
+#[derive(Error, Debug, PartialEq)]
+pub enum DecodeError {
+    #[error("invalid percent-encoding")]
+    InvalidEscape,
+    #[error("encoded slash or NUL")]
+    ForbiddenByte,
+    #[error("path is not valid UTF-8")]
+    InvalidUtf8,
+}
+
+/// Decodes percent-escapes in a URL path.
+///
+/// Encoded `/` and NUL are rejected, as they would change the meaning of the decoded path.
+pub fn decode_path(path: &str) -> Result<String, DecodeError> {
+    let src = path.as_bytes();
+    let mut out = Vec::with_capacity(src.len());
+    let mut i = 0;
+    while i < src.len() {
+        if src[i] != b'%' {
+            out.push(src[i]);
+            i += 1;
+            continue;
+        }
+
+        // `from_str_radix` alone would accept a sign, e.g. `%+1`.
+        let byte = src
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or(DecodeError::InvalidEscape)?;
+
+        if matches!(byte, b'/' | b'\0') {
+            return Err(DecodeError::ForbiddenByte);
+        }
+        out.push(byte);
+        i += 3;
+    }
+
+    String::from_utf8(out).map_err(|_| DecodeError::InvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_escapes() {
+        assert_eq!(decode_path("/a%20b/%C3%A9").unwrap(), "/a b/é");
+        assert_eq!(decode_path("/%2e%2E/x").unwrap(), "/../x");
+        assert_eq!(decode_path("/plain").unwrap(), "/plain");
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        for path in ["/%", "/%2", "/%zz", "/%+1", "/%-1", "/% 1"] {
+            assert_eq!(decode_path(path), Err(DecodeError::InvalidEscape), "{path}");
+        }
+    }
+
+    #[test]
+    fn rejects_forbidden_bytes() {
+        assert_eq!(decode_path("/a%2fb"), Err(DecodeError::ForbiddenByte));
+        assert_eq!(decode_path("/a%00"), Err(DecodeError::ForbiddenByte));
+    }
+
+    #[test]
+    fn rejects_invalid_utf8() {
+        assert_eq!(decode_path("/%ff"), Err(DecodeError::InvalidUtf8));
+    }
+}
@@ -1,10 +1,11 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::upper_case_acronyms)]
 
 mod handler;
 mod http;
 use std::env;
 
-use http::Server;
+use handler::{Mounts, StaticHandler, Uploads, VirtualHosts, WebDav};
+use http::{Compression, Handler, Server};
 
 fn main() {
     // Use build directory as root if public dir is not defined
@@ -12,7 +13,55 @@ fn main() {
         .unwrap_or(format!("{}/public", env!("CARGO_MANIFEST_DIR")).to_string());
 
     println!("Serving files from {}", static_path);
-    let handler = handler::StaticHandler::new(static_path);
-    let srv = Server::new("127.0.0.1:8080".to_string(), &handler);
+
+    // Allow publishing files with `PUT` and `DELETE` using the bearer token.
+    let uploads = || {
+        env::var("UPLOAD_TOKEN").ok().map(|token| {
+            Uploads::default()
+                .with_bearer_token(&token)
+                .with_create_dirs(true)
+        })
+    };
+
+    let mut public = StaticHandler::new(static_path);
+    if let Some(uploads) = uploads() {
+        public = public.with_uploads(uploads);
+    }
+
+    let mut site = Mounts::default().with_root("/", public);
+    if let Ok(mounts) = env::var("MOUNTS") {
+        // Comma-separated `prefix=dir` pairs served with alias semantics.
+        for (prefix, dir) in mounts.split(',').filter_map(|m| m.split_once('=')) {
+            println!("Serving {prefix} from {dir}");
+            site = site.with_alias(prefix.trim(), StaticHandler::new(dir.trim().to_string()));
+        }
+    }
+
+    let virtual_hosts = env::var("VIRTUAL_HOSTS").unwrap_or_default();
+    let webdav_hosts = env::var("WEBDAV_HOSTS").unwrap_or_default();
+    let handler: Box<dyn Handler> = if virtual_hosts.is_empty() && webdav_hosts.is_empty() {
+        Box::new(site)
+    } else {
+        // Comma-separated `host=dir` pairs, public dir serves other hosts.
+        let mut vhosts = VirtualHosts::default().with_default(site);
+        for (host, dir) in virtual_hosts.split(',').filter_map(|h| h.split_once('=')) {
+            println!("Serving {host} from {dir}");
+            vhosts = vhosts.with_host(host.trim(), StaticHandler::new(dir.trim().to_string()));
+        }
+
+        // WebDAV hosts are writable with the upload token, read-only without it.
+        for (host, dir) in webdav_hosts.split(',').filter_map(|h| h.split_once('=')) {
+            println!("Serving {host} from {dir} over WebDAV");
+            let mut dav = StaticHandler::new(dir.trim().to_string());
+            if let Some(uploads) = uploads() {
+                dav = dav.with_uploads(uploads);
+            }
+            vhosts = vhosts.with_host(host.trim(), WebDav::new(dav));
+        }
+        Box::new(vhosts)
+    };
+
+    let srv = Server::new("127.0.0.1:8080".to_string(), handler.as_ref())
+        .with_compression(Compression::default());
     srv.start();
 }